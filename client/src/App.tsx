@@ -85,6 +85,15 @@ export default function App() {
         setStatic('route_plugins', res.route_plugins || [])
         setStatic('clustering_plugins', res.clustering_plugins || [])
         setStatic('bootstrap_plugins', res.bootstrap_plugins || false)
+        setStatic('route_plugin_manifests', res.route_plugin_manifests || {})
+        setStatic(
+          'clustering_plugin_manifests',
+          res.clustering_plugin_manifests || {},
+        )
+        setStatic(
+          'bootstrap_plugin_manifests',
+          res.bootstrap_plugin_manifests || {},
+        )
         if (!res.logged_in) {
           router.navigate('/login')
         }
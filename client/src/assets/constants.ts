@@ -209,6 +209,7 @@ export const CLUSTERING_MODES = [
   'Balanced',
   'Better',
   'Best',
+  'Exact',
 ] as const
 
 export const SORT_BY = [
@@ -218,4 +219,5 @@ export const SORT_BY = [
   'Geohash',
   'LatLon',
   'PointCount',
+  'NativeTsp',
 ] as const
@@ -141,6 +141,7 @@ export interface KojiRoute extends BasicKojiEntry {
   description?: string
   geometry: MultiPoint
   points: number
+  open_route?: boolean
 }
 
 export interface KojiTileServer extends BasicKojiEntry {
@@ -157,6 +158,26 @@ export interface AdminProject extends KojiProject {
   geofences: number[]
 }
 
+export interface KojiProgress {
+  phase: string
+  percent: number
+  cancelled: boolean
+}
+
+export interface KojiLongHop {
+  from: [number, number]
+  to: [number, number]
+  distance: number
+  fillers: number
+}
+
+export interface KojiPluginReport {
+  plugin: string
+  stats: Record<string, unknown>
+  properties: { point: [number, number]; properties: Record<string, unknown> }[]
+  errors: { code: string; message: string }[]
+}
+
 export interface KojiStats {
   best_clusters: [number, number][]
   best_cluster_point_count: number
@@ -164,11 +185,33 @@ export interface KojiStats {
   route_time: number
   total_points: number
   points_covered: number
+  total_weight: number
+  weight_covered: number
+  points_per_radius: Record<string, number>
+  points_per_coverage: Record<string, number>
   total_clusters: number
   total_distance: number
   longest_distance: number
+  seed_distance: number
+  distance_saved: number
+  stitch_saved: number
+  baseline_circles: number
+  optimized_circles: number
+  open_route: boolean
+  total_cycle_time: number
+  longest_hop_time: number
+  hop_times: number[]
+  long_hops: KojiLongHop[]
+  filled_hops: KojiLongHop[]
   fetch_time: number
   mygod_score: number
+  lower_bound: number
+  optimality_gap: number
+  seed: number | null
+  device_distances: number[]
+  device_cycle_times: number[]
+  device_imbalance: number
+  plugin: KojiPluginReport | null
 }
 
 export interface KojiResponse<T = FeatureCollection> {
@@ -208,6 +251,26 @@ export interface PixiMarker {
   p: [number, number]
 }
 
+export interface KojiPluginArg {
+  type: 'string' | 'number' | 'integer' | 'boolean'
+  default?: string | number | boolean
+  required: boolean
+  description?: string
+  options?: (string | number | boolean)[]
+}
+
+export interface KojiPluginManifest {
+  display_name: string | null
+  description: string | null
+  interpreter: string | null
+  entry: string | null
+  protocol: number | null
+  timeout: number | null
+  worker: boolean | null
+  args: Record<string, KojiPluginArg>
+  categories: string[]
+}
+
 export interface Config {
   start_lat: number
   start_lon: number
@@ -218,6 +281,9 @@ export interface Config {
   route_plugins: string[]
   clustering_plugins: string[]
   bootstrap_plugins: string[]
+  route_plugin_manifests: Record<string, KojiPluginManifest>
+  clustering_plugin_manifests: Record<string, KojiPluginManifest>
+  bootstrap_plugin_manifests: Record<string, KojiPluginManifest>
 }
 
 export type CombinedState = Partial<UsePersist> & Partial<UseStatic>
@@ -50,6 +50,8 @@ function TimerComponent() {
 export default function Loading() {
   const loading = useStatic((s) => s.loading)
   const loadingAbort = useStatic((s) => s.loadingAbort)
+  const loadingJobs = useStatic((s) => s.loadingJobs)
+  const progress = useStatic((s) => s.progress)
   const totalLoadingTime = useStatic((s) => s.totalLoadingTime)
   const setStatic = useStatic((s) => s.setStatic)
 
@@ -74,6 +76,11 @@ export default function Loading() {
       open={!!Object.keys(loading).length}
       onClick={() => {
         Object.values(loadingAbort).forEach((fn) => fn?.abort())
+        if (!totalLoadingTime) {
+          Object.values(loadingJobs).forEach((id) =>
+            fetch(`/api/v1/calc/progress/${id}`, { method: 'DELETE' }),
+          )
+        }
         return setStatic('loading', {})
       }}
       sx={{
@@ -211,9 +218,19 @@ export default function Loading() {
                   </Typography>
                 </>
               ) : (
-                <CircularProgress
-                  size={`calc(100vh / ${Object.keys(loading).length * 3})`}
-                />
+                <>
+                  <CircularProgress
+                    size={`calc(100vh / ${Object.keys(loading).length * 3})`}
+                    variant={progress[key] ? 'determinate' : 'indeterminate'}
+                    value={progress[key]?.percent}
+                  />
+                  {progress[key] && (
+                    <Typography variant="subtitle2">
+                      {fromSnakeCase(progress[key].phase)} |{' '}
+                      {progress[key].percent.toFixed(0)}%
+                    </Typography>
+                  )}
+                </>
               )}
             </Grid2>
           )}
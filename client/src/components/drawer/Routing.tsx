@@ -19,6 +19,7 @@ import {
   SORT_BY,
   TTH,
 } from '@assets/constants'
+import { KojiPluginManifest } from '@assets/types'
 import { useStatic } from '@hooks/useStatic'
 import { usePersist } from '@hooks/usePersist'
 import { clusteringRouting } from '@services/fetches'
@@ -36,6 +37,17 @@ const formatPluginName = (item: string) => {
   }
   return item
 }
+const argsHelperText = (manifest?: KojiPluginManifest) => {
+  if (!manifest || !Object.keys(manifest.args).length) return '--x 1 --y abc'
+  return Object.entries(manifest.args)
+    .map(
+      ([key, arg]) =>
+        `--${key} <${arg.type}>${
+          arg.default === undefined ? '' : ` = ${arg.default}`
+        }${arg.required ? ' (required)' : ''}`,
+    )
+    .join(' ')
+}
 export default function RoutingTab() {
   const mode = usePersist((s) => s.mode)
   const category = usePersist((s) => s.category)
@@ -51,6 +63,9 @@ export default function RoutingTab() {
   const routePlugins = useStatic((s) => s.route_plugins)
   const clusteringPlugins = useStatic((s) => s.clustering_plugins)
   const bootstrapPlugins = useStatic((s) => s.bootstrap_plugins)
+  const routeManifests = useStatic((s) => s.route_plugin_manifests)
+  const clusteringManifests = useStatic((s) => s.clustering_plugin_manifests)
+  const bootstrapManifests = useStatic((s) => s.bootstrap_plugin_manifests)
 
   const sortByOptions = React.useMemo(() => {
     return [...SORT_BY, ...routePlugins]
@@ -96,7 +111,10 @@ export default function RoutingTab() {
           !CALC_MODE.some((x) => x === calculation_mode) && mode === 'bootstrap'
         }
       >
-        <UserTextInput field="bootstrapping_args" helperText="--x 1 --y abc" />
+        <UserTextInput
+          field="bootstrapping_args"
+          helperText={argsHelperText(bootstrapManifests[calculation_mode])}
+        />
       </Collapse>
       <Collapse in={calculation_mode === 'Radius'}>
         <UserTextInput field="radius" />
@@ -135,7 +153,9 @@ export default function RoutingTab() {
           hideLabel
           buttons={clusterOptions}
           type="select"
-          itemLabel={formatPluginName}
+          itemLabel={(item) =>
+            clusteringManifests[item]?.display_name || formatPluginName(item)
+          }
         />
         <Collapse in={!fastest}>
           <UserTextInput field="cluster_split_level" min={1} max={20} />
@@ -143,14 +163,19 @@ export default function RoutingTab() {
         <Collapse in={!fastest}>
           <UserTextInput field="max_clusters" min={0} />
         </Collapse>
+        <UserTextInput field="min_coverage" min={1} />
         <Collapse in={!CLUSTERING_MODES.some((m) => m === cluster_mode)}>
-          <UserTextInput field="clustering_args" helperText="--x 1 --y abc" />
+          <UserTextInput
+            field="clustering_args"
+            helperText={argsHelperText(clusteringManifests[cluster_mode])}
+          />
         </Collapse>
         <Toggle field="center_clusters" label="Center Clusters" />
-        {/* <Toggle
+        <Toggle field="weighted" label="Weighted Data Points" />
+        <Toggle
           field="genetic_post_processing"
           label="Genetic Post Processing"
-        /> */}
+        />
       </Collapse>
 
       <Divider sx={{ my: 2 }} />
@@ -159,7 +184,9 @@ export default function RoutingTab() {
         field="sort_by"
         buttons={sortByOptions}
         type="select"
-        itemLabel={formatPluginName}
+        itemLabel={(item) =>
+          routeManifests[item]?.display_name || formatPluginName(item)
+        }
       />
       <Collapse in={!SORT_BY.some((sort) => sort === sort_by)}>
         <UserTextInput field="route_split_level" min={1} max={12} />
@@ -167,7 +194,10 @@ export default function RoutingTab() {
       <Collapse
         in={!SORT_BY.some((sort) => sort === sort_by) && sort_by !== 'tsp'}
       >
-        <UserTextInput field="routing_args" helperText="--x 1 --y abc" />
+        <UserTextInput
+          field="routing_args"
+          helperText={argsHelperText(routeManifests[sort_by])}
+        />
       </Collapse>
 
       <Divider sx={{ my: 2 }} />
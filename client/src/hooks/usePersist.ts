@@ -75,11 +75,13 @@ export interface UsePersist {
   s2_level: typeof S2_CELL_LEVELS[number]
   s2_size: typeof BOOTSTRAP_LEVELS[number]
   max_clusters: number
+  min_coverage: number
   routing_args: string
   clustering_args: string
   bootstrapping_args: string
   center_clusters: boolean
   genetic_post_processing: boolean
+  weighted: boolean
   // generations: number | ''
   // routing_time: number | ''
   // devices: number | ''
@@ -132,9 +134,11 @@ export const usePersist = create(
       // routing_chunk_size: 0,
       calculation_mode: 'Radius',
       genetic_post_processing: false,
+      weighted: false,
       s2_level: 15,
       s2_size: 9,
       max_clusters: 0,
+      min_coverage: 1,
       min_points: 3,
       save_to_db: false,
       save_to_scanner: false,
@@ -4,6 +4,7 @@ import type { AlertProps } from '@mui/material'
 import type {
   AdminProject,
   KojiStats,
+  KojiProgress,
   FeatureCollection,
   StoreNoFn,
   KojiTileServer,
@@ -28,6 +29,8 @@ export interface UseStatic {
   }
   loading: Record<string, KojiStats | null | false>
   loadingAbort: Record<string, AbortController | null>
+  loadingJobs: Record<string, string>
+  progress: Record<string, KojiProgress>
   updateButton: boolean
   totalStartTime: number
   totalLoadingTime: number
@@ -35,6 +38,9 @@ export interface UseStatic {
   route_plugins: string[]
   clustering_plugins: string[]
   bootstrap_plugins: string[]
+  route_plugin_manifests: Config['route_plugin_manifests']
+  clustering_plugin_manifests: Config['clustering_plugin_manifests']
+  bootstrap_plugin_manifests: Config['bootstrap_plugin_manifests']
   tileServers: KojiTileServer[]
   kojiRoutes: { name: string; id: number; type: string }[]
   scannerRoutes: { name: string; id: number; type: string }[]
@@ -97,6 +103,8 @@ export const useStatic = create<UseStatic>((set, get) => ({
   },
   loading: {},
   loadingAbort: {},
+  loadingJobs: {},
+  progress: {},
   totalStartTime: 0,
   totalLoadingTime: 0,
   updateButton: false,
@@ -116,6 +124,9 @@ export const useStatic = create<UseStatic>((set, get) => ({
   route_plugins: [],
   clustering_plugins: [],
   bootstrap_plugins: [],
+  route_plugin_manifests: {},
+  clustering_plugin_manifests: {},
+  bootstrap_plugin_manifests: {},
   layerEditing: {
     cutMode: false,
     dragMode: false,
@@ -134,10 +134,12 @@ export async function clusteringRouting({
     s2_level,
     s2_size,
     max_clusters,
+    min_coverage,
     routing_args,
     clustering_args,
     bootstrapping_args,
     genetic_post_processing,
+    weighted,
   } = usePersist.getState()
   const { geojson, setStatic, bounds } = useStatic.getState()
   const { add, activeRoute } = useShapes.getState().setters
@@ -193,6 +195,14 @@ export async function clusteringRouting({
         new AbortController(),
       ]),
     ),
+    loadingJobs: Object.fromEntries(
+      areas.map((k) => [
+        getFromKojiKey(k.id as string)?.name ||
+          `${k.geometry.type}${k.id ? `-${k.id}` : ''}`,
+        crypto.randomUUID(),
+      ]),
+    ),
+    progress: {},
     totalStartTime,
     totalLoadingTime: 0,
   })
@@ -202,6 +212,18 @@ export async function clusteringRouting({
       const fenceRef = getFromKojiKey(area.id as string)
       const routeRef = getRouteByCategory(category, fenceRef?.name)
       const startTime = Date.now()
+      const key =
+        fenceRef?.name ||
+        `${area.geometry.type}${area.id ? `-${area.id}` : ''}`
+      const job_id = useStatic.getState().loadingJobs[key]
+
+      const events = new EventSource(`/api/v1/calc/progress/${job_id}`)
+      events.onmessage = (e) =>
+        setStatic('progress', (prev) => ({
+          ...prev,
+          [key]: JSON.parse(e.data),
+        }))
+      events.addEventListener('done', () => events.close())
 
       const res = await fetch(
         mode === 'bootstrap'
@@ -248,6 +270,7 @@ export async function clusteringRouting({
             sort_by,
             tth,
             max_clusters,
+            min_coverage,
             calculation_mode,
             s2_level,
             s2_size,
@@ -255,9 +278,11 @@ export async function clusteringRouting({
             clustering_args,
             bootstrapping_args,
             genetic_post_processing,
+            weighted,
+            job_id,
           }),
         },
-      )
+      ).finally(() => events.close())
       if (!res.ok) {
         if (fenceRef?.name) {
           setStatic('loading', (prev) => ({
@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use geo::{BoundingRect, Distance, Euclidean, MapCoords, Point, Polygon};
+use model::api::Precision;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::progress::Progress;
+
+/// The hex lattice looks the same after turning it by 60 degrees
+const MAX_ROTATION: Precision = 60.;
+/// Candidates evaluated in parallel between the checks of the time budget
+const BATCH: usize = 64;
+/// The search stops after this many candidates even when time is left
+const MAX_CANDIDATES: usize = 4_096;
+const EARTH_RADIUS: Precision = 6_371_008.8;
+
+/// Equirectangular projection to meters around the center of a polygon, accurate enough for one area
+struct Projection {
+    lat: Precision,
+    lng: Precision,
+    cos: Precision,
+}
+
+impl Projection {
+    fn new(center: Point) -> Self {
+        Self {
+            lat: center.y(),
+            lng: center.x(),
+            cos: center.y().to_radians().cos(),
+        }
+    }
+
+    fn to_plane(&self, point: Point) -> Point {
+        Point::new(
+            (point.x() - self.lng).to_radians() * EARTH_RADIUS * self.cos,
+            (point.y() - self.lat).to_radians() * EARTH_RADIUS,
+        )
+    }
+
+    fn to_sphere(&self, point: Point) -> Point {
+        Point::new(
+            self.lng + (point.x() / (EARTH_RADIUS * self.cos)).to_degrees(),
+            self.lat + (point.y() / EARTH_RADIUS).to_degrees(),
+        )
+    }
+
+    /// Largest factor east-west distances between `min_lat` and `max_lat` are off by in the plane, either way
+    fn distortion(&self, min_lat: Precision, max_lat: Precision) -> Precision {
+        [min_lat, max_lat, 0_f64.clamp(min_lat, max_lat)]
+            .into_iter()
+            .map(|lat| {
+                let ratio = lat.clamp(-89., 89.).to_radians().cos() / self.cos;
+                ratio.max(1. / ratio)
+            })
+            .fold(1., Precision::max)
+    }
+}
+
+/// Rotation in degrees and phase offset of a lattice, the offset is a fraction of each lattice vector
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    rotation: Precision,
+    offset: [Precision; 2],
+}
+
+impl Candidate {
+    /// Halton sequence, spreads any number of candidates evenly and starts with the unrotated lattice
+    fn nth(index: usize) -> Self {
+        let halton = |base: usize| {
+            let mut index = index;
+            let mut fraction = 1.;
+            let mut result = 0.;
+            while index > 0 {
+                fraction /= base as Precision;
+                result += fraction * (index % base) as Precision;
+                index /= base;
+            }
+            result
+        };
+        Self {
+            rotation: halton(2) * MAX_ROTATION,
+            offset: [halton(3), halton(5)],
+        }
+    }
+}
+
+/// Circles of a hex lattice that touch a polygon, in the plane of its [Projection]
+struct Lattice {
+    polygon: Polygon,
+    /// Circle radius in the plane, shrunk so the circles still cover the polygon on the sphere
+    radius: Precision,
+    /// Distance a circle may have to the polygon in the plane and still touch it on the sphere
+    reach: Precision,
+}
+
+impl Lattice {
+    /// Runs `visit` for every circle of the candidate that touches the polygon,
+    /// stops early and returns `None` once more than `limit` circles were found
+    fn circles(
+        &self,
+        candidate: Candidate,
+        limit: usize,
+        mut visit: impl FnMut(Point),
+    ) -> Option<usize> {
+        // the lattice stays axis aligned, the polygon is turned the other way instead
+        let (sin, cos) = candidate.rotation.to_radians().sin_cos();
+        let polygon = self
+            .polygon
+            .map_coords(|coord| rotate(Point::from(coord), -sin, cos).into());
+        let bounds = polygon.bounding_rect()?;
+
+        // a circle covers everything within its radius when the rows are 1.5 radii apart
+        let dx = 3_f64.sqrt() * self.radius;
+        let dy = 1.5 * self.radius;
+        let origin_x = candidate.offset[0] * dx + candidate.offset[1] * dx / 2.;
+        let origin_y = candidate.offset[1] * dy;
+
+        let first_row = ((bounds.min().y - self.reach - origin_y) / dy).floor() as i64;
+        let last_row = ((bounds.max().y + self.reach - origin_y) / dy).ceil() as i64;
+        let mut count = 0;
+        for row in first_row..=last_row {
+            let y = origin_y + row as Precision * dy;
+            let row_x = origin_x + row as Precision * dx / 2.;
+            let first_column = ((bounds.min().x - self.reach - row_x) / dx).floor() as i64;
+            let last_column = ((bounds.max().x + self.reach - row_x) / dx).ceil() as i64;
+            for column in first_column..=last_column {
+                let center = Point::new(row_x + column as Precision * dx, y);
+                if Euclidean.distance(&center, &polygon) <= self.reach {
+                    count += 1;
+                    if count > limit {
+                        return None;
+                    }
+                    visit(rotate(center, sin, cos));
+                }
+            }
+        }
+        Some(count)
+    }
+}
+
+fn rotate(point: Point, sin: Precision, cos: Precision) -> Point {
+    Point::new(
+        point.x() * cos - point.y() * sin,
+        point.x() * sin + point.y() * cos,
+    )
+}
+
+/// Searches lattice rotations and phase offsets in parallel for `time_budget` after `started` for the
+/// arrangement with the fewest circles that cover the polygon. `None` unless it needs fewer than `baseline` circles
+pub fn optimize(
+    polygon: &Polygon,
+    radius: Precision,
+    baseline: usize,
+    started: Instant,
+    time_budget: Duration,
+    progress: &Progress,
+) -> Option<Vec<Point>> {
+    let bounds = polygon.bounding_rect()?;
+    let projection = Projection::new(bounds.center().into());
+    let margin = (radius / EARTH_RADIUS).to_degrees();
+    let distortion =
+        projection.distortion(bounds.min().y - margin, bounds.max().y + margin) * 1.001;
+    let lattice = Lattice {
+        polygon: polygon.map_coords(|coord| projection.to_plane(coord.into()).into()),
+        radius: radius / distortion,
+        reach: radius * distortion,
+    };
+
+    let best_count = AtomicUsize::new(baseline.saturating_sub(1));
+    let mut best: Option<(usize, Candidate)> = None;
+    let mut tried = 0;
+    while tried < MAX_CANDIDATES && started.elapsed() < time_budget && !progress.is_cancelled() {
+        let batch: Vec<(usize, Candidate)> = (tried..(tried + BATCH).min(MAX_CANDIDATES))
+            .into_par_iter()
+            .filter_map(|index| {
+                let candidate = Candidate::nth(index);
+                let limit = best_count.load(Ordering::Relaxed);
+                let count = lattice.circles(candidate, limit, |_| {})?;
+                best_count.fetch_min(count, Ordering::Relaxed);
+                Some((count, candidate))
+            })
+            .collect();
+        tried += BATCH;
+        for (count, candidate) in batch {
+            if best.is_none_or(|(best, _)| count < best) {
+                best = Some((count, candidate));
+            }
+        }
+    }
+
+    let (count, candidate) = best?;
+    log::debug!(
+        "lattice rotated by {:.1}° needs {count} instead of {baseline} circles, {tried} candidates tried",
+        candidate.rotation
+    );
+    let mut circles = Vec::with_capacity(count);
+    lattice.circles(candidate, usize::MAX, |center| {
+        circles.push(projection.to_sphere(center))
+    });
+    Some(circles)
+}
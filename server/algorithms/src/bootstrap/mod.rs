@@ -1,68 +1,74 @@
-use std::time::Instant;
+use std::{collections::BTreeMap, time::Instant};
 
 use geojson::{Feature, FeatureCollection};
-use model::api::{Precision, ToFeature, calc_mode::CalculationMode, sort_by::SortBy};
+use model::api::{ToFeature, calc_mode::CalculationMode};
 
 use crate::{
-    plugin::{Folder, Plugin},
+    plugin::{Folder, Plugin, discovery, manifest::Manifest},
     stats::Stats,
-    utils,
+    strategy::{self, BootstrapInput},
 };
 
+mod lattice;
 pub mod radius;
 pub mod s2;
+pub(crate) mod strategies;
 
-pub fn main(
-    area: FeatureCollection,
-    calculation_mode: CalculationMode,
-    radius: Precision,
-    sort_by: SortBy,
-    s2_level: u8,
-    s2_size: u8,
-    route_split_level: u64,
-    stats: &mut Stats,
-    routing_args: &str,
-    bootstrapping_rags: &str,
-) -> Vec<Feature> {
+/// Covers every feature of the area with the strategy the input picks, one routed feature each
+pub fn main(area: FeatureCollection, input: &BootstrapInput, stats: &mut Stats) -> Vec<Feature> {
+    let BootstrapInput {
+        ref calculation_mode,
+        radius,
+        args: bootstrapping_rags,
+        exclusions,
+        progress,
+        ..
+    } = *input;
     let mut features = vec![];
+    let total = area.features.len();
 
-    for feature in area.features {
-        match &calculation_mode {
-            CalculationMode::Radius => {
-                let mut new_radius = radius::BootstrapRadius::new(&feature, radius);
-                new_radius.sort(&sort_by, route_split_level, routing_args);
-
-                *stats += &new_radius.stats;
-                features.push(new_radius.feature());
-            }
-            CalculationMode::S2 => {
-                let mut new_s2 = s2::BootstrapS2::new(&feature, s2_level, s2_size);
-                new_s2.sort(&sort_by, route_split_level, routing_args);
-
-                *stats += &new_s2.stats;
-                features.push(new_s2.feature());
-            }
-            CalculationMode::Custom(plugin) => {
-                match Plugin::new(plugin, Folder::Bootstrap, 0, bootstrapping_rags) {
-                    Ok(plugin_manager) => {
-                        let time = Instant::now();
-                        match plugin_manager.run(feature.to_string()) {
-                            Ok(sorted_clusters) => {
-                                let mut plugin_stats = Stats::new(plugin.to_string(), 0);
-                                plugin_stats.set_cluster_time(time);
-                                plugin_stats.cluster_stats(0., &vec![], &sorted_clusters);
-                                features.push(sorted_clusters.to_feature(None));
-                                *stats += &plugin_stats;
-                            }
-                            Err(e) => {
-                                log::error!("Error while running plugin: {}", e);
-                            }
-                        }
+    for (index, feature) in area.features.into_iter().enumerate() {
+        if progress.is_cancelled() {
+            log::warn!(
+                "bootstrapping was cancelled after {} of {} areas",
+                index,
+                total
+            );
+            break;
+        }
+        progress.set_phase(&format!("bootstrapping {} of {}", index + 1, total));
+        let strategy_name = match calculation_mode {
+            CalculationMode::Radius => "radius",
+            CalculationMode::S2 => "s2",
+            CalculationMode::Custom(plugin) => plugin,
+        };
+        if let Some(strategy) = strategy::BOOTSTRAP.get(strategy_name) {
+            features.extend(strategy.bootstrap(&feature, input, stats));
+            continue;
+        }
+        match Plugin::new(strategy_name, Folder::Bootstrap, 0, bootstrapping_rags) {
+            Ok(mut plugin_manager) => {
+                plugin_manager
+                    .set_radius(radius)
+                    .set_progress(progress.clone());
+                let time = Instant::now();
+                match plugin_manager.run_feature(&feature) {
+                    Ok(sorted_clusters) => {
+                        let sorted_clusters = exclusions.relocate_all(sorted_clusters, radius);
+                        let mut plugin_stats = Stats::new(strategy_name.to_string(), 0);
+                        plugin_stats.set_cluster_time(time);
+                        plugin_stats.cluster_stats(0., &vec![], &sorted_clusters);
+                        features.push(sorted_clusters.to_feature(None));
+                        *stats += &plugin_stats;
                     }
                     Err(e) => {
-                        log::error!("Plugin not found: {}", e);
+                        log::error!("Error while running plugin: {}", e);
                     }
                 }
+                stats.add_plugin_report(plugin_manager.report());
+            }
+            Err(e) => {
+                log::error!("Plugin not found: {}", e);
             }
         }
     }
@@ -70,12 +76,22 @@ pub fn main(
 }
 
 pub fn bootstrap_plugins() -> Vec<String> {
-    utils::get_plugin_list("algorithms/src/bootstrap/plugins").unwrap_or(vec![])
+    discovery::plugins(Folder::Bootstrap)
+}
+
+pub fn bootstrap_plugin_manifests() -> BTreeMap<String, Manifest> {
+    Manifest::all(Folder::Bootstrap)
+}
+
+/// Registered strategies that don't ship with Kōji, followed by the plugins
+pub fn custom_bootstrap_options() -> Vec<String> {
+    let mut options = strategy::BOOTSTRAP.custom_names();
+    options.extend(bootstrap_plugins());
+    options
 }
 
 pub fn all_bootstrap_options() -> Vec<String> {
-    let mut options = bootstrap_plugins();
-    options.push("radius".to_string());
-    options.push("s2".to_string());
+    let mut options = custom_bootstrap_options();
+    options.extend(strategy::BOOTSTRAP.builtin_names());
     options
 }
@@ -1,11 +1,15 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::{routing, stats::Stats};
+use super::lattice;
+use crate::{exclusion::Exclusions, progress::Progress, routing, stats::Stats};
 
 use geo::{Contains, Destination, Distance, Extremes, Haversine, Point, Polygon};
 use geojson::{Feature, Geometry, Value};
 use model::{
-    api::{Precision, ToFeature, ToGeometryVec, single_vec::SingleVec, sort_by::SortBy},
+    api::{
+        Precision, ToFeature, ToGeometryVec, args::RouteStart, single_vec::SingleVec,
+        sort_by::SortBy,
+    },
     db::sea_orm_active_enums::Type,
 };
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
@@ -37,7 +41,60 @@ impl<'a> BootstrapRadius<'a> {
         new_bootstrap
     }
 
-    pub fn sort(&mut self, sort_by: &SortBy, route_split_level: u64, routing_args: &str) {
+    /// Searches the rotation and phase offset of the hex lattice of every polygon for the one with the fewest circles,
+    /// keeping the fixed lattice of a polygon unless an arrangement with fewer circles was found within the time budget
+    pub fn optimize(&mut self, time_budget: Duration, progress: &Progress) {
+        let time = Instant::now();
+        let polygons: Vec<(usize, Vec<Point>)> = self
+            .polygons()
+            .par_iter()
+            .map(|geometry| {
+                let baseline = self.generate_circles(geometry);
+                let optimized = Polygon::<Precision>::try_from(geometry)
+                    .ok()
+                    .and_then(|polygon| {
+                        lattice::optimize(
+                            &polygon,
+                            self.radius,
+                            baseline.len(),
+                            time,
+                            time_budget,
+                            progress,
+                        )
+                    });
+                (baseline.len(), optimized.unwrap_or(baseline))
+            })
+            .collect();
+
+        let baseline = polygons.iter().map(|(baseline, _)| baseline).sum();
+        self.result = polygons
+            .into_iter()
+            .flat_map(|(_, circles)| circles)
+            .map(|p| [p.y(), p.x()])
+            .collect();
+        self.stats.set_lattice_circles(baseline, self.result.len());
+        self.stats.cluster_time += time.elapsed().as_secs_f64();
+        self.stats.cluster_stats(self.radius, &vec![], &self.result);
+    }
+
+    /// Moves the circles centered in an exclusion just outside of it, dropping those that only reached into exclusions
+    pub fn exclude(&mut self, exclusions: &Exclusions) {
+        if exclusions.is_empty() {
+            return;
+        }
+        self.result = exclusions.relocate_all(std::mem::take(&mut self.result), self.radius);
+        self.stats.cluster_stats(self.radius, &vec![], &self.result);
+    }
+
+    pub fn sort(
+        &mut self,
+        sort_by: &SortBy,
+        route_split_level: u64,
+        routing_args: &str,
+        seed: u64,
+        route_start: &RouteStart,
+        progress: &Progress,
+    ) {
         self.result = routing::main(
             &vec![],
             self.result.clone(),
@@ -46,6 +103,9 @@ impl<'a> BootstrapRadius<'a> {
             self.radius,
             &mut self.stats,
             routing_args,
+            seed,
+            route_start,
+            progress,
         );
     }
 
@@ -63,6 +123,7 @@ impl<'a> BootstrapRadius<'a> {
             new_feature.set_property("__geofence_id", geofence_id.clone());
         }
         new_feature.set_property("__mode", "CirclePokemon");
+        new_feature.set_property("__open_route", self.stats.open_route);
         new_feature
     }
 
@@ -74,17 +135,19 @@ impl<'a> BootstrapRadius<'a> {
     }
 
     fn flatten_circles(&self) -> Vec<Point> {
-        if let Some(geometry) = self.feature.geometry.clone() {
-            match geometry.value {
-                Value::MultiPolygon(_) => geometry
-                    .to_geometry_vec()
-                    .par_iter()
-                    .flat_map(|geo| self.generate_circles(geo))
-                    .collect(),
-                _ => self.generate_circles(&geometry),
-            }
-        } else {
-            vec![]
+        self.polygons()
+            .par_iter()
+            .flat_map(|geo| self.generate_circles(geo))
+            .collect()
+    }
+
+    fn polygons(&self) -> Vec<Geometry> {
+        match self.feature.geometry.clone() {
+            Some(geometry) => match geometry.value {
+                Value::MultiPolygon(_) => geometry.to_geometry_vec(),
+                _ => vec![geometry],
+            },
+            None => vec![],
         }
     }
 
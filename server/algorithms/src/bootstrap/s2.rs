@@ -1,11 +1,11 @@
 use std::{collections::HashSet, time::Instant};
 
-use crate::{routing, stats::Stats};
+use crate::{exclusion::Exclusions, progress::Progress, routing, stats::Stats};
 
-use geo::{BoundingRect, MultiPolygon, Polygon};
+use geo::{BoundingRect, Distance, Haversine, MultiPolygon, Point, Polygon};
 use geojson::{Feature, Value};
 use model::{
-    api::{Precision, ToFeature, single_vec::SingleVec, sort_by::SortBy},
+    api::{Precision, ToFeature, args::RouteStart, single_vec::SingleVec, sort_by::SortBy},
     db::sea_orm_active_enums::Type,
 };
 use rayon::{iter::IntoParallelIterator, prelude::ParallelIterator};
@@ -47,7 +47,49 @@ impl<'a> BootstrapS2<'a> {
         new_bootstrap
     }
 
-    pub fn sort(&mut self, sort_by: &SortBy, route_split_level: u64, routing_args: &str) {
+    /// Moves the blocks centered in an exclusion just outside of it, as long as the block still reaches its
+    /// original center, otherwise the block is dropped
+    pub fn exclude(&mut self, exclusions: &Exclusions) {
+        if exclusions.is_empty() {
+            return;
+        }
+        let total = self.result.len();
+        self.result = self
+            .result
+            .iter()
+            .filter_map(|center| exclusions.relocate(*center, self.reach(*center)))
+            .collect();
+        log::info!(
+            "dropped {} S2 blocks that only reached into exclusions",
+            total - self.result.len()
+        );
+        self.stats.cluster_stats(0., &vec![], &self.result);
+    }
+
+    /// Meters from the center of the block around the point to its corners
+    fn reach(&self, center: [Precision; 2]) -> Precision {
+        let id = CellID::from(LatLng::from_degrees(center[0], center[1])).parent(self.level as u64);
+        let cell = Cell::from(id);
+        let to_point = |point| {
+            let ll = LatLng::from(&point);
+            Point::new(ll.lng.deg(), ll.lat.deg())
+        };
+        let cell_center = to_point(cell.center());
+        (0..4)
+            .map(|k| Haversine.distance(cell_center, to_point(cell.vertex(k))))
+            .fold(0., Precision::max)
+            * self.size as Precision
+    }
+
+    pub fn sort(
+        &mut self,
+        sort_by: &SortBy,
+        route_split_level: u64,
+        routing_args: &str,
+        seed: u64,
+        route_start: &RouteStart,
+        progress: &Progress,
+    ) {
         self.result = routing::main(
             &vec![],
             self.result.clone(),
@@ -56,6 +98,9 @@ impl<'a> BootstrapS2<'a> {
             0.,
             &mut self.stats,
             routing_args,
+            seed,
+            route_start,
+            progress,
         );
     }
 
@@ -73,6 +118,7 @@ impl<'a> BootstrapS2<'a> {
             new_feature.set_property("__geofence_id", geofence_id.clone());
         }
         new_feature.set_property("__mode", "CircleRaid");
+        new_feature.set_property("__open_route", self.stats.open_route);
         new_feature
     }
 
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use geojson::Feature;
+
+use super::{radius::BootstrapRadius, s2::BootstrapS2};
+use crate::{
+    stats::Stats,
+    strategy::{BootstrapInput, BootstrapStrategy},
+};
+
+pub(crate) fn builtins() -> Vec<(&'static str, Arc<dyn BootstrapStrategy>)> {
+    vec![("radius", Arc::new(Radius)), ("s2", Arc::new(S2))]
+}
+
+struct Radius;
+
+impl BootstrapStrategy for Radius {
+    fn bootstrap(
+        &self,
+        feature: &Feature,
+        input: &BootstrapInput,
+        stats: &mut Stats,
+    ) -> Option<Feature> {
+        let mut new_radius = BootstrapRadius::new(feature, input.radius);
+        if input.optimize_lattice {
+            new_radius.optimize(input.lattice_time_budget, input.progress);
+        }
+        new_radius.exclude(input.exclusions);
+        new_radius.sort(
+            input.sort_by,
+            input.route_split_level,
+            input.routing_args,
+            input.seed,
+            input.route_start,
+            input.progress,
+        );
+
+        *stats += &new_radius.stats;
+        Some(new_radius.feature())
+    }
+}
+
+struct S2;
+
+impl BootstrapStrategy for S2 {
+    fn bootstrap(
+        &self,
+        feature: &Feature,
+        input: &BootstrapInput,
+        stats: &mut Stats,
+    ) -> Option<Feature> {
+        let mut new_s2 = BootstrapS2::new(feature, input.s2_level, input.s2_size);
+        new_s2.exclude(input.exclusions);
+        new_s2.sort(
+            input.sort_by,
+            input.route_split_level,
+            input.routing_args,
+            input.seed,
+            input.route_start,
+            input.progress,
+        );
+
+        *stats += &new_s2.stats;
+        Some(new_s2.feature())
+    }
+}
@@ -1,5 +1,3 @@
-use std::cell::RefCell;
-
 use macros::time;
 use model::api::{Precision, single_vec::SingleVec};
 use rand::{Rng, SeedableRng, rngs::SmallRng};
@@ -55,25 +53,29 @@ impl BBox {
     }
 }
 
-thread_local! {
-    static TLS_RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
-}
-
 pub fn generate_clusters_from_points(
     points: &[[Precision; 2]],
     radius_meters: Precision,
     density: usize,
+    seed: u64,
 ) -> SingleVec {
     let center = utils::centroid(points);
-    generate_cluster_candidates_grid(points, meters_to_degrees(radius_meters, center[0]), density)
+    generate_cluster_candidates_grid(
+        points,
+        meters_to_degrees(radius_meters, center[0]),
+        density,
+        seed,
+    )
 }
 
-/// Generate candidates using a grid-based approach for better coverage
+/// Generate candidates using a grid-based approach for better coverage.
+/// The jitter only depends on the seed, so the same input always returns the same candidates
 #[time()]
 pub fn generate_cluster_candidates_grid(
     points: &[[Precision; 2]],
     cluster_radius_deg: Precision,
     grid_density: usize,
+    seed: u64,
 ) -> SingleVec {
     // Quick exit for empty input
     if points.is_empty() || grid_density == 0 {
@@ -96,28 +98,20 @@ pub fn generate_cluster_candidates_grid(
         return vec![[lat, lon]];
     }
 
-    // Known final length → collect directly, no intermediate Vecs per row.
-    let total = grid_density * grid_density;
-
-    (0..total)
+    // Each row gets its own RNG so the jitter doesn't depend on how rayon splits the work
+    (0..grid_density)
         .into_par_iter()
-        .map(|idx| {
-            // Map a flat index -> (i, j)
-            let i = idx / grid_density;
-            let j = idx % grid_density;
-
-            // Jitter within the cell [0, step)
-            let (dj_lat, dj_lon) = TLS_RNG.with(|cell| {
-                let mut rng = cell.borrow_mut();
-                (
-                    rng.random::<Precision>() * lat_step,
-                    rng.random::<Precision>() * lon_step,
-                )
-            });
-
-            let lat = bbox.min_lat + (i as Precision) * lat_step + dj_lat;
-            let lon = bbox.min_lon + (j as Precision) * lon_step + dj_lon;
-            [lat, lon]
+        .flat_map_iter(|i| {
+            let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(i as u64));
+            (0..grid_density).map(move |j| {
+                // Jitter within the cell [0, step)
+                let dj_lat = rng.random::<Precision>() * lat_step;
+                let dj_lon = rng.random::<Precision>() * lon_step;
+
+                let lat = bbox.min_lat + (i as Precision) * lat_step + dj_lat;
+                let lon = bbox.min_lon + (j as Precision) * lon_step + dj_lon;
+                [lat, lon]
+            })
         })
         .collect()
 }
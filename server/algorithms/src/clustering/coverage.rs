@@ -0,0 +1,250 @@
+use std::{cmp::Ordering, collections::BinaryHeap, time::Instant};
+
+use hashbrown::{HashMap, HashSet};
+use model::api::{Precision, single_vec::SingleVec};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use rstar::RTree;
+
+use crate::{
+    exclusion::Exclusions,
+    rtree::{self, Radii, Weights, point::Point},
+};
+
+use super::candidates;
+
+const BYTE: usize = 1024;
+
+/// Candidate ordered by how much under-covered weight it would cover, gains only ever shrink
+/// so a stale entry is recomputed and pushed back instead of rescanning every candidate
+struct Gain(Precision, usize);
+
+impl PartialEq for Gain {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Gain {}
+
+impl PartialOrd for Gain {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Gain {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .total_cmp(&other.0)
+            .then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+fn key(point: &Point) -> [u64; 2] {
+    point.center.map(Precision::to_bits)
+}
+
+/// Coordinates of the points, duplicated data points are in the tree once each but a cluster covers them once
+fn keys<'a>(points: impl IntoIterator<Item = &'a Point>) -> HashSet<[u64; 2]> {
+    points.into_iter().map(key).collect()
+}
+
+/// How often every coordinate is covered by the clusters
+fn coverage(point_tree: &RTree<Point>, clusters: &SingleVec) -> HashMap<[u64; 2], usize> {
+    let mut coverage: HashMap<[u64; 2], usize> = HashMap::new();
+    for cluster in clusters.iter() {
+        for key in keys(point_tree.locate_all_at_point(cluster)) {
+            *coverage.entry(key).or_insert(0) += 1;
+        }
+    }
+    coverage
+}
+
+/// Greedily adds the candidate covering the most weight that's still short of `required`
+/// until no candidate helps or `max_clusters` is reached. Candidates inside of the exclusions are skipped.
+/// Returns the clusters and how often every coordinate is covered
+#[allow(clippy::too_many_arguments)]
+fn cover(
+    point_tree: &RTree<Point>,
+    points: &SingleVec,
+    clusters: SingleVec,
+    required: impl Fn(&Point) -> usize,
+    max_clusters: usize,
+    radius: Precision,
+    seed: u64,
+    exclusions: &Exclusions,
+) -> (SingleVec, HashMap<[u64; 2], usize>) {
+    let mut coverage = coverage(point_tree, &clusters);
+    let deficit = |coverage: &HashMap<[u64; 2], usize>, point: &Point| {
+        required(point).saturating_sub(coverage.get(&key(point)).copied().unwrap_or(0))
+    };
+
+    let existing: HashSet<[u64; 2]> = clusters
+        .iter()
+        .map(|cluster| cluster.map(Precision::to_bits))
+        .collect();
+    let mut centers = candidates::generate_clusters_from_points(points, radius, BYTE, seed);
+    centers.extend(points.iter().copied());
+    centers.retain(|center| {
+        !existing.contains(&center.map(Precision::to_bits)) && !exclusions.contains(center)
+    });
+    centers.sort_by(|a, b| a[0].total_cmp(&b[0]).then_with(|| a[1].total_cmp(&b[1])));
+    centers.dedup();
+
+    let covers: Vec<Vec<&Point>> = centers
+        .par_iter()
+        .map(|center| point_tree.locate_all_at_point(center).collect())
+        .collect();
+    let gain = |coverage: &HashMap<[u64; 2], usize>, covered: &[&Point]| -> Precision {
+        covered
+            .iter()
+            .filter(|point| deficit(coverage, point) > 0)
+            .map(|point| point.weight.max(Precision::EPSILON))
+            .sum()
+    };
+
+    let mut heap: BinaryHeap<Gain> = covers
+        .iter()
+        .enumerate()
+        .map(|(index, covered)| Gain(gain(&coverage, covered), index))
+        .filter(|entry| entry.0 > 0.)
+        .collect();
+
+    let mut clusters = clusters;
+    while clusters.len() < max_clusters {
+        let Some(Gain(stale, index)) = heap.pop() else {
+            break;
+        };
+        let current = gain(&coverage, &covers[index]);
+        if current <= 0. {
+            continue;
+        }
+        if current < stale && heap.peek().is_some_and(|next| next.0 > current) {
+            heap.push(Gain(current, index));
+            continue;
+        }
+        for key in keys(covers[index].iter().copied()) {
+            *coverage.entry(key).or_insert(0) += 1;
+        }
+        clusters.push(centers[index]);
+    }
+    (clusters, coverage)
+}
+
+/// Adds clusters until every data point sits inside at least `min_coverage` of them,
+/// or until `max_clusters` is reached. Existing clusters are kept as they are
+#[allow(clippy::too_many_arguments)]
+pub fn fill(
+    points: &SingleVec,
+    clusters: SingleVec,
+    radius: Precision,
+    min_coverage: usize,
+    max_clusters: usize,
+    weights: &Weights,
+    radii: &Radii,
+    seed: u64,
+    exclusions: &Exclusions,
+) -> SingleVec {
+    if min_coverage <= 1 || points.is_empty() {
+        return clusters;
+    }
+    let time = Instant::now();
+    let point_tree = rtree::spawn_with(radius, points, weights, radii);
+
+    let initial = clusters.len();
+    let (clusters, coverage) = cover(
+        &point_tree,
+        points,
+        clusters,
+        |_| min_coverage,
+        max_clusters,
+        radii.min(radius),
+        seed,
+        exclusions,
+    );
+
+    let missing = point_tree
+        .iter()
+        .filter(|point| coverage.get(&key(point)).copied().unwrap_or(0) < min_coverage)
+        .count();
+    if missing > 0 {
+        log::warn!(
+            "{} data points are covered by fewer than {} clusters{}",
+            missing,
+            min_coverage,
+            if clusters.len() >= max_clusters {
+                ", max_clusters was reached"
+            } else {
+                ""
+            }
+        );
+    }
+    log::info!(
+        "added {} clusters for {}x coverage in {:.2}s",
+        clusters.len() - initial,
+        min_coverage,
+        time.elapsed().as_secs_f32()
+    );
+    clusters
+}
+
+/// Moves the centers out of the exclusions, then adds clusters outside of them for the data points
+/// that were covered before and lost their coverage because a center was moved or dropped
+#[allow(clippy::too_many_arguments)]
+pub fn relocate(
+    points: &SingleVec,
+    clusters: SingleVec,
+    radius: Precision,
+    max_clusters: usize,
+    weights: &Weights,
+    radii: &Radii,
+    seed: u64,
+    exclusions: &Exclusions,
+) -> SingleVec {
+    if exclusions.is_empty() || points.is_empty() {
+        return clusters;
+    }
+    let time = Instant::now();
+    let point_tree = rtree::spawn_with(radius, points, weights, radii);
+    let min_radius = radii.min(radius);
+
+    let before = coverage(&point_tree, &clusters);
+    let clusters = exclusions.relocate_all(clusters, min_radius);
+    let after = coverage(&point_tree, &clusters);
+    let lost: HashSet<[u64; 2]> = before
+        .keys()
+        .filter(|key| !after.contains_key(*key))
+        .copied()
+        .collect();
+    if lost.is_empty() {
+        return clusters;
+    }
+
+    let initial = clusters.len();
+    let (clusters, coverage) = cover(
+        &point_tree,
+        points,
+        clusters,
+        |point| usize::from(lost.contains(&key(point))),
+        max_clusters,
+        min_radius,
+        seed,
+        exclusions,
+    );
+    let missing = lost
+        .iter()
+        .filter(|key| !coverage.contains_key(*key))
+        .count();
+    if missing > 0 {
+        log::warn!(
+            "{} coordinates lost their coverage to exclusions and could not be covered again",
+            missing
+        );
+    }
+    log::info!(
+        "added {} clusters for the coverage moved out of exclusions in {:.2}s",
+        clusters.len() - initial,
+        time.elapsed().as_secs_f32()
+    );
+    clusters
+}
@@ -0,0 +1,608 @@
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use model::api::{Precision, cluster_mode::ClusterMode, single_vec::SingleVec};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use rstar::RTree;
+
+use crate::{
+    progress::Progress,
+    rtree::{self, Radii, Weights, point::Point},
+};
+
+use super::greedy::Greedy;
+
+/// Subgradient steps used to tighten the lower bound at the root
+const LAGRANGIAN_ITERATIONS: usize = 500;
+/// Guards the bound comparisons against float noise
+const EPSILON: Precision = 1e-9;
+
+pub struct Solution {
+    pub clusters: SingleVec,
+    /// No selection from the candidate set can score lower than this,
+    /// equal to the score of `clusters` when the search finished within the time budget
+    pub lower_bound: Precision,
+}
+
+/// Minimum set cover over the greedy candidates, where every picked cluster costs `min_points`
+/// and leaving a point uncovered costs its weight, so the objective matches the mygod score
+struct Instance {
+    set_cost: Precision,
+    penalties: Vec<Precision>,
+    centers: SingleVec,
+    sets: Vec<Vec<usize>>,
+    element_sets: Vec<Vec<usize>>,
+    /// Elements with the fewest covering sets first, the order that branching and the bounds use
+    order: Vec<usize>,
+    /// All costs are whole numbers, true for unweighted points
+    integral: bool,
+}
+
+impl Instance {
+    fn new(
+        set_cost: Precision,
+        penalties: Vec<Precision>,
+        covers: Vec<([Precision; 2], Vec<usize>)>,
+    ) -> Self {
+        let mut covers = covers;
+        covers.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.1.cmp(&b.1)));
+        covers.dedup_by(|a, b| a.1 == b.1);
+
+        // every set costs the same, so a set contained in another one is never needed
+        let mut element_sets: Vec<Vec<usize>> = vec![vec![]; penalties.len()];
+        let mut centers = vec![];
+        let mut sets: Vec<Vec<usize>> = vec![];
+        for (center, set) in covers.into_iter() {
+            let dominated = element_sets[set[0]]
+                .iter()
+                .any(|&other| is_subset(&set, &sets[other]));
+            if dominated {
+                continue;
+            }
+            for &element in set.iter() {
+                element_sets[element].push(sets.len());
+            }
+            centers.push(center);
+            sets.push(set);
+        }
+
+        let mut order: Vec<usize> = (0..penalties.len()).collect();
+        order.sort_by_key(|&element| element_sets[element].len());
+        let integral = std::iter::once(&set_cost)
+            .chain(penalties.iter())
+            .all(|cost| cost.fract() == 0.);
+
+        Self {
+            set_cost,
+            penalties,
+            centers,
+            sets,
+            element_sets,
+            order,
+            integral,
+        }
+    }
+
+    /// Any score is a whole number when all costs are, so the bound can be rounded up
+    fn round_bound(&self, bound: Precision) -> Precision {
+        if self.integral {
+            (bound - EPSILON).ceil()
+        } else {
+            bound
+        }
+    }
+
+    /// Only worth leaving a point uncovered when its weight is below the cost of a cluster
+    fn can_skip(&self, element: usize) -> bool {
+        self.penalties[element] < self.set_cost
+    }
+
+    fn score(&self, chosen: &[usize]) -> Precision {
+        let mut covered = vec![false; self.penalties.len()];
+        for &set in chosen {
+            for &element in self.sets[set].iter() {
+                covered[element] = true;
+            }
+        }
+        chosen.len() as Precision * self.set_cost
+            + covered
+                .iter()
+                .enumerate()
+                .filter(|(_, covered)| !**covered)
+                .map(|(element, _)| self.penalties[element])
+                .sum::<Precision>()
+    }
+
+    /// Cost-effectiveness greedy, used as the first incumbent
+    fn greedy(&self) -> Vec<usize> {
+        let mut covered = vec![false; self.penalties.len()];
+        let mut chosen = vec![];
+        loop {
+            let best = (0..self.sets.len())
+                .map(|set| {
+                    let gain: Precision = self.sets[set]
+                        .iter()
+                        .filter(|&&element| !covered[element])
+                        .map(|&element| self.penalties[element])
+                        .sum();
+                    (set, gain)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            match best {
+                Some((set, gain)) if gain > 0. && gain + EPSILON >= self.set_cost => {
+                    for &element in self.sets[set].iter() {
+                        covered[element] = true;
+                    }
+                    chosen.push(set);
+                }
+                _ => break,
+            }
+        }
+        self.prune(chosen)
+    }
+
+    /// Drops sets whose elements are all covered by the other chosen sets
+    fn prune(&self, mut chosen: Vec<usize>) -> Vec<usize> {
+        let mut counts = vec![0; self.penalties.len()];
+        for &set in chosen.iter() {
+            for &element in self.sets[set].iter() {
+                counts[element] += 1;
+            }
+        }
+        chosen.sort_by_key(|&set| self.sets[set].len());
+        chosen.retain(|&set| {
+            let redundant = self.sets[set].iter().all(|&element| counts[element] > 1);
+            if redundant {
+                for &element in self.sets[set].iter() {
+                    counts[element] -= 1;
+                }
+            }
+            !redundant
+        });
+        chosen
+    }
+
+    /// Feasible dual of the LP relaxation, built by raising each open element as far as its sets allow.
+    /// `residual` has to hold `set_cost` for every set and is restored before returning
+    fn dual_bound(&self, open: impl Fn(usize) -> bool, residual: &mut [Precision]) -> Precision {
+        let mut bound = 0.;
+        let mut touched = vec![];
+        for &element in self.order.iter() {
+            if !open(element) {
+                continue;
+            }
+            let raise = self.element_sets[element]
+                .iter()
+                .map(|&set| residual[set])
+                .fold(self.penalties[element], Precision::min);
+            if raise <= 0. {
+                continue;
+            }
+            bound += raise;
+            for &set in self.element_sets[element].iter() {
+                if residual[set] == self.set_cost {
+                    touched.push(set);
+                }
+                residual[set] -= raise;
+            }
+        }
+        for set in touched {
+            residual[set] = self.set_cost;
+        }
+        bound
+    }
+
+    /// Subgradient optimization of the Lagrangian relaxation, gets close to the LP bound
+    fn lagrangian_bound(&self, upper_bound: Precision) -> Precision {
+        let elements = self.penalties.len();
+        let mut multipliers = self.penalties.clone();
+        let mut best = 0.;
+        let mut step = 2.;
+        let mut stale = 0;
+        for _ in 0..LAGRANGIAN_ITERATIONS {
+            let mut value: Precision = multipliers.iter().sum();
+            let mut subgradient = vec![1.; elements];
+            for members in self.sets.iter() {
+                let reduced =
+                    self.set_cost - members.iter().map(|&e| multipliers[e]).sum::<Precision>();
+                if reduced < 0. {
+                    value += reduced;
+                    for &element in members.iter() {
+                        subgradient[element] -= 1.;
+                    }
+                }
+            }
+            for element in 0..elements {
+                let reduced = self.penalties[element] - multipliers[element];
+                if reduced < 0. {
+                    value += reduced;
+                    subgradient[element] -= 1.;
+                }
+            }
+            if value > best + EPSILON {
+                best = value;
+                stale = 0;
+            } else {
+                stale += 1;
+                if stale >= 20 {
+                    step /= 2.;
+                    stale = 0;
+                }
+            }
+            let norm: Precision = subgradient.iter().map(|g| g * g).sum();
+            if norm < EPSILON || self.round_bound(best) >= upper_bound - EPSILON || step < 1e-4 {
+                break;
+            }
+            let length = step * (upper_bound - value) / norm;
+            for element in 0..elements {
+                multipliers[element] =
+                    (multipliers[element] + length * subgradient[element]).max(0.);
+            }
+        }
+        best
+    }
+}
+
+fn is_subset(small: &[usize], large: &[usize]) -> bool {
+    let mut large = large.iter();
+    small
+        .iter()
+        .all(|element| large.any(|other| other == element))
+}
+
+struct Search<'a> {
+    instance: &'a Instance,
+    progress: &'a Progress,
+    start: Instant,
+    time_budget: Duration,
+    timed_out: bool,
+    nodes: usize,
+    coverage: Vec<usize>,
+    skipped: Vec<bool>,
+    residual: Vec<Precision>,
+    chosen: Vec<usize>,
+    best: Vec<usize>,
+    best_score: Precision,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        instance: &'a Instance,
+        progress: &'a Progress,
+        time_budget: Duration,
+        incumbent: Vec<usize>,
+    ) -> Self {
+        Self {
+            instance,
+            progress,
+            start: Instant::now(),
+            time_budget,
+            timed_out: false,
+            nodes: 0,
+            coverage: vec![0; instance.penalties.len()],
+            skipped: vec![false; instance.penalties.len()],
+            residual: vec![instance.set_cost; instance.sets.len()],
+            chosen: vec![],
+            best_score: instance.score(&incumbent),
+            best: incumbent,
+        }
+    }
+
+    fn is_open(&self, element: usize) -> bool {
+        self.coverage[element] == 0 && !self.skipped[element]
+    }
+
+    fn add(&mut self, set: usize) {
+        for &element in self.instance.sets[set].iter() {
+            self.coverage[element] += 1;
+        }
+        self.chosen.push(set);
+    }
+
+    fn remove(&mut self, set: usize) {
+        for &element in self.instance.sets[set].iter() {
+            self.coverage[element] -= 1;
+        }
+        self.chosen.pop();
+    }
+
+    fn run(&mut self, cost: Precision) {
+        if self.timed_out {
+            return;
+        }
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) {
+            let elapsed = self.start.elapsed();
+            if elapsed > self.time_budget || self.progress.is_cancelled() {
+                self.timed_out = true;
+                return;
+            }
+            self.progress
+                .set_percent(elapsed.as_secs_f32() / self.time_budget.as_secs_f32() * 100.);
+        }
+        let instance = self.instance;
+        let Some(element) = instance.order.iter().copied().find(|&e| self.is_open(e)) else {
+            if cost < self.best_score - EPSILON {
+                self.best_score = cost;
+                self.best = self.chosen.clone();
+            }
+            return;
+        };
+        let mut residual = std::mem::take(&mut self.residual);
+        let bound = instance.dual_bound(|e| self.is_open(e), &mut residual);
+        self.residual = residual;
+        if instance.round_bound(cost + bound) >= self.best_score - EPSILON {
+            return;
+        }
+
+        let mut branches: Vec<(usize, usize)> = instance.element_sets[element]
+            .iter()
+            .map(|&set| {
+                let gain = instance.sets[set]
+                    .iter()
+                    .filter(|&&e| self.is_open(e))
+                    .count();
+                (set, gain)
+            })
+            .collect();
+        branches.sort_by_key(|&(_, gain)| std::cmp::Reverse(gain));
+        for (set, _) in branches {
+            self.add(set);
+            self.run(cost + instance.set_cost);
+            self.remove(set);
+        }
+        if instance.can_skip(element) {
+            self.skipped[element] = true;
+            self.run(cost + instance.penalties[element]);
+            self.skipped[element] = false;
+        }
+    }
+}
+
+pub struct Exact {
+    radius: Precision,
+    min_points: usize,
+    max_clusters: usize,
+    weights: Weights,
+    radii: Radii,
+    seed: u64,
+    time_budget: Duration,
+    progress: Progress,
+}
+
+impl Default for Exact {
+    fn default() -> Self {
+        Exact {
+            radius: 70.,
+            min_points: 1,
+            max_clusters: usize::MAX,
+            weights: Weights::default(),
+            radii: Radii::default(),
+            seed: rand::random(),
+            time_budget: Duration::from_secs(60),
+            progress: Progress::default(),
+        }
+    }
+}
+
+impl Exact {
+    pub fn set_radius(&mut self, radius: Precision) -> &mut Self {
+        self.radius = radius;
+        self
+    }
+    pub fn set_min_points(&mut self, min_points: usize) -> &mut Self {
+        self.min_points = min_points;
+        self
+    }
+    pub fn set_max_clusters(&mut self, max_clusters: usize) -> &mut Self {
+        self.max_clusters = max_clusters;
+        self
+    }
+    pub fn set_weights(&mut self, weights: Weights) -> &mut Self {
+        self.weights = weights;
+        self
+    }
+    pub fn set_radii(&mut self, radii: Radii) -> &mut Self {
+        self.radii = radii;
+        self
+    }
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+    /// The search returns the best cover found so far once this runs out
+    pub fn set_time_budget(&mut self, time_budget: Duration) -> &mut Self {
+        self.time_budget = time_budget;
+        self
+    }
+    /// The search stops early when the progress is cancelled, keeping the best cover found so far
+    pub fn set_progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
+
+    fn instance(&self, points: &SingleVec, point_tree: &RTree<Point>) -> Instance {
+        // duplicate points are always covered together, so they count as one heavier point
+        let key = |point: &Point| point.center.map(Precision::to_bits);
+        let mut index = HashMap::new();
+        let mut penalties: Vec<Precision> = vec![];
+        for point in point_tree.iter() {
+            let element = *index.entry(key(point)).or_insert_with(|| {
+                penalties.push(0.);
+                penalties.len() - 1
+            });
+            penalties[element] += point.weight;
+        }
+
+        // the Best candidates plus every point itself, so that each point can be covered
+        let mut greedy = Greedy::default();
+        greedy
+            .set_cluster_mode(ClusterMode::Best)
+            .set_radius(self.radius)
+            .set_radii(self.radii.clone())
+            .set_seed(self.seed);
+        let mut centers = greedy.candidates(points, point_tree);
+        centers.extend(points.iter().copied());
+
+        let covers: Vec<([Precision; 2], Vec<usize>)> = centers
+            .into_par_iter()
+            .filter_map(|center| {
+                let mut set: Vec<usize> = point_tree
+                    .locate_all_at_point(&center)
+                    .map(|point| index[&key(point)])
+                    .collect();
+                set.sort_unstable();
+                set.dedup();
+                (!set.is_empty()).then_some((center, set))
+            })
+            .collect();
+        log::info!("collected {} candidate clusters", covers.len());
+
+        Instance::new(self.min_points as Precision, penalties, covers)
+    }
+
+    /// Branch and bound from the greedy cover, the best selection of sets and a lower bound on its score,
+    /// which equals the score unless the search was stopped by the time budget or the progress
+    fn search(&self, instance: &Instance, time: Instant) -> (Vec<usize>, Precision) {
+        let incumbent = instance.greedy();
+        let greedy_score = instance.score(&incumbent);
+        let mut residual = vec![instance.set_cost; instance.sets.len()];
+        let root_bound = instance.round_bound(
+            instance
+                .dual_bound(|_| true, &mut residual)
+                .max(instance.lagrangian_bound(greedy_score)),
+        );
+
+        let mut search = Search::new(instance, &self.progress, self.time_budget, incumbent);
+        if root_bound < greedy_score - EPSILON {
+            search.run(0.);
+        }
+        let proven = !search.timed_out;
+        let score = search.best_score;
+        let lower_bound = if proven { score } else { root_bound.min(score) };
+
+        log::info!(
+            "exact cover finished in {:.2}s after {} nodes | greedy: {:.2} | best: {:.2} | lower bound: {:.2}{}",
+            time.elapsed().as_secs_f32(),
+            search.nodes,
+            greedy_score,
+            score,
+            lower_bound,
+            if proven {
+                ""
+            } else if self.progress.is_cancelled() {
+                " | cancelled"
+            } else {
+                " | time budget ran out"
+            },
+        );
+
+        (search.best, lower_bound)
+    }
+
+    pub fn run(&self, points: &SingleVec) -> Solution {
+        let time = Instant::now();
+        let point_tree = rtree::spawn_with(self.radius, points, &self.weights, &self.radii);
+        let instance = self.instance(points, &point_tree);
+        log::info!(
+            "reduced to {} non-dominated candidates for {} points",
+            instance.sets.len(),
+            instance.penalties.len()
+        );
+
+        let (mut best, lower_bound) = self.search(&instance, time);
+
+        // the bound ignores `max_clusters`, it stays valid for the truncated cover
+        if best.len() > self.max_clusters {
+            log::warn!(
+                "exact cover needs {} clusters, keeping the {} that cover the most weight",
+                best.len(),
+                self.max_clusters
+            );
+            let weight = |set: usize| -> Precision {
+                instance.sets[set]
+                    .iter()
+                    .map(|&element| instance.penalties[element])
+                    .sum()
+            };
+            best.sort_by(|a, b| weight(*b).total_cmp(&weight(*a)));
+            best.truncate(self.max_clusters);
+        }
+
+        Solution {
+            clusters: best.iter().map(|&set| instance.centers[set]).collect(),
+            lower_bound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every element in a set of its own plus some random sets, the same on every run.
+    /// The search doesn't look at the centers
+    fn instance(seed: u64, set_cost: Precision, penalties: Vec<Precision>) -> Instance {
+        let mut state = seed;
+        let mut next = |below: usize| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize % below
+        };
+        let elements = penalties.len();
+        let mut covers: Vec<([Precision; 2], Vec<usize>)> = (0..elements)
+            .map(|element| ([0., 0.], vec![element]))
+            .collect();
+        for _ in 0..12 {
+            let mut set: Vec<usize> = (0..2 + next(4)).map(|_| next(elements)).collect();
+            set.sort_unstable();
+            set.dedup();
+            covers.push(([0., 0.], set));
+        }
+        Instance::new(set_cost, penalties, covers)
+    }
+
+    /// Lowest score over every selection of the sets
+    fn brute_force(instance: &Instance) -> Precision {
+        assert!(instance.sets.len() <= 20, "too many sets to brute force");
+        (0..1u32 << instance.sets.len())
+            .map(|mask| {
+                let chosen: Vec<usize> = (0..instance.sets.len())
+                    .filter(|set| mask & (1 << set) != 0)
+                    .collect();
+                instance.score(&chosen)
+            })
+            .fold(Precision::MAX, Precision::min)
+    }
+
+    fn check(instance: &Instance) {
+        let (best, lower_bound) = Exact::default().search(instance, Instant::now());
+        let score = instance.score(&best);
+        let optimum = brute_force(instance);
+        assert!((score - optimum).abs() < 1e-6, "{score} != {optimum}");
+        assert!(lower_bound <= score + 1e-6, "{lower_bound} > {score}");
+        // nothing stops the search early, so the bound is proven
+        assert!(
+            (lower_bound - optimum).abs() < 1e-6,
+            "{lower_bound} != {optimum}"
+        );
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..20 {
+            check(&instance(seed, 3., vec![1.; 10]));
+        }
+    }
+
+    #[test]
+    fn matches_brute_force_weighted() {
+        for seed in 0..20 {
+            let penalties = (0..10)
+                .map(|element| 0.4 + (element % 4) as Precision * 0.9)
+                .collect();
+            check(&instance(seed, 2.5, penalties));
+        }
+    }
+}
@@ -2,9 +2,10 @@ use geo::Coord;
 use hashbrown::HashSet;
 use model::api::single_vec::SingleVec;
 use rstar::PointDistance;
+use s2::{cellid::CellID, latlng::LatLng};
 use std::collections::HashMap;
 
-use crate::project::Plane;
+use crate::{project::Plane, rtree::Weights};
 
 #[derive(Debug, Clone)]
 struct BoundingBox {
@@ -64,18 +65,39 @@ impl FromKey for String {
     }
 }
 
-pub fn main(input: &SingleVec, radius: f64, min_points: usize) -> Vec<[f64; 2]> {
+/// Sums the weight of the projected point keys, points without a weight count as `1.0`
+fn weigh(points: &[String], point_weights: &HashMap<String, f64>) -> f64 {
+    points
+        .iter()
+        .map(|p| point_weights.get(p).copied().unwrap_or(1.))
+        .sum()
+}
+
+pub fn main(input: &SingleVec, radius: f64, min_points: usize, weights: &Weights) -> Vec<[f64; 2]> {
     let plane = Plane::new(input).radius(radius);
     let output = plane.project();
 
-    let point_map = cluster(output, min_points);
+    let point_weights: HashMap<String, f64> = if weights.is_empty() {
+        HashMap::new()
+    } else {
+        output
+            .iter()
+            .zip(input.iter())
+            .map(|(coord, p)| {
+                let cell_id = CellID::from(LatLng::from_degrees(p[0], p[1])).parent(20);
+                (coord.to_key(), weights.get(&cell_id))
+            })
+            .collect()
+    };
+
+    let point_map = cluster(output, min_points, &point_weights);
 
     let output = {
         let mut seen_map: HashSet<String> = HashSet::new();
         let return_value: SingleVec = point_map
             .into_iter()
             .filter_map(|(key, values)| {
-                if values.len() >= min_points {
+                if weigh(&values, &point_weights) >= min_points as f64 {
                     for point in values.into_iter() {
                         seen_map.insert(point);
                     }
@@ -97,7 +119,11 @@ fn update(point_map: &mut ClusterMap, key: PointTuple, p: Coord) {
     });
 }
 
-fn cluster(points: Vec<Coord>, min_points: usize) -> HashMap<String, Vec<String>> {
+fn cluster(
+    points: Vec<Coord>,
+    min_points: usize,
+    point_weights: &HashMap<String, f64>,
+) -> HashMap<String, Vec<String>> {
     let sqrt2: f64 = 2.0_f64.sqrt();
     let additive_factor: f64 = sqrt2 / 2.;
     let sqrt2_x_one_point_five_minus_one: f64 = (sqrt2 * 1.5) - 1.;
@@ -182,7 +208,11 @@ fn cluster(points: Vec<Coord>, min_points: usize) -> HashMap<String, Vec<String>
         }
     };
 
-    'count: for (point, (bb, _check, _second, points)) in udc_point_map.clone().into_iter() {
+    // cells are paired up with their neighbors in order, sorting them keeps the result reproducible
+    let mut cells: Vec<_> = udc_point_map.clone().into_iter().collect();
+    cells.sort_by_key(|(point, _)| *point);
+
+    'count: for (point, (bb, _check, _second, points)) in cells.into_iter() {
         let (v, h) = point.clone();
 
         for (v, h, _index) in [
@@ -216,7 +246,7 @@ fn cluster(points: Vec<Coord>, min_points: usize) -> HashMap<String, Vec<String>
                 if lower_left.distance_2(&upper_right) <= 4. {
                     let mut combined = points.clone();
                     combined.extend(found_cluster.3.clone());
-                    if combined.len() > min_points {
+                    if weigh(&combined, point_weights) > min_points as f64 {
                         let [x, y] = lower_left.midpoint(&upper_right);
                         process_final(Coord { x, y }, combined);
                         udc_point_map
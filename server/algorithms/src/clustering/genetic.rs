@@ -1,10 +1,13 @@
 use macros::time;
 use model::api::single_vec::SingleVec;
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::SmallRng};
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 
-use crate::{clustering::candidates, rtree};
+use crate::{clustering::candidates, progress::Progress, rtree, stats::Stats};
 
 #[derive(Clone, Debug)]
 struct Individual {
@@ -18,7 +21,11 @@ pub struct GeneticClusterOptimizer {
     max_clusters: usize,
     radius: f64, // radius in meters
     population_size: usize,
-    generations: usize,
+    time_budget: Duration,
+    seed: u64,
+    weights: Vec<f64>,
+    radii: Vec<f64>,
+    progress: Progress,
     mutation_rate: f64,
     crossover_rate: f64,
 }
@@ -36,20 +43,58 @@ impl GeneticClusterOptimizer {
             max_clusters,
             radius,
             population_size: 100,
-            generations: 200,
+            time_budget: Duration::from_secs(10),
+            seed: rand::random(),
+            weights: vec![],
+            radii: vec![],
+            progress: Progress::default(),
             mutation_rate: 0.3,
             crossover_rate: 0.7,
         }
     }
 
+    /// Wall-clock time the generations are allowed to run for, evaluation of the final population is not included
+    pub fn set_time_budget(&mut self, time_budget: Duration) -> &mut Self {
+        self.time_budget = time_budget;
+        self
+    }
+
+    /// Seeds every random step, a random seed is used when unset
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Data point weights, only used when comparing the final solution against the initial one
+    pub fn set_weights(&mut self, weights: Vec<f64>) -> &mut Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Per data point radii, only used when comparing the final solution against the initial one
+    pub fn set_radii(&mut self, radii: Vec<f64>) -> &mut Self {
+        self.radii = radii;
+        self
+    }
+
+    /// Generations stop early when the progress is cancelled
+    pub fn set_progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
+
     #[time("genetic optimizing")]
     pub fn optimize(&self, initial_clusters: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
-        let mut rng = rand::rng();
+        let time = Instant::now();
+        let mut rng = SmallRng::seed_from_u64(self.seed);
 
         // Initialize population (coverage-aware, bounded, deduped)
         let mut population = self.initialize_population(&initial_clusters, &mut rng);
 
-        for generation in 0..self.generations {
+        let mut generation = 0;
+        while time.elapsed() < self.time_budget && !self.progress.is_cancelled() {
+            self.progress
+                .set_percent(time.elapsed().as_secs_f32() / self.time_budget.as_secs_f32() * 100.);
             // Evaluate fitness
             population.par_iter_mut().for_each(|individual| {
                 individual.fitness = self.calculate_fitness(&individual.clusters);
@@ -72,12 +117,13 @@ impl GeneticClusterOptimizer {
             let mut new_population = Vec::with_capacity(self.population_size);
             new_population.extend_from_slice(&population[0..elite_count]);
 
-            // Offspring
+            // Offspring, each with its own RNG derived from the main one so the result only depends on the seed
             let offspring_count = self.population_size - elite_count;
-            let offspring: Vec<Individual> = (0..offspring_count)
+            let offspring_seeds: Vec<u64> = (0..offspring_count).map(|_| rng.random()).collect();
+            let offspring: Vec<Individual> = offspring_seeds
                 .into_par_iter()
-                .map(|_| {
-                    let mut rng = rand::rng();
+                .map(|seed| {
+                    let mut rng = SmallRng::seed_from_u64(seed);
                     let parent1 = self.tournament_selection(&population, &mut rng);
                     let parent2 = self.tournament_selection(&population, &mut rng);
 
@@ -96,6 +142,7 @@ impl GeneticClusterOptimizer {
 
             new_population.extend(offspring);
             population = new_population;
+            generation += 1;
         }
 
         // Final evaluation
@@ -106,13 +153,33 @@ impl GeneticClusterOptimizer {
 
         let best = &population[0];
         let covered = self.count_covered_points(&best.clusters);
-        log::info!("Optimization complete!");
+        log::info!("Optimization complete after {} generations!", generation);
         log::info!("Final best fitness: {:.2}", best.fitness);
         log::info!("Total clusters: {}", best.clusters.len());
         log::info!("Points covered: {} / {}", covered, self.data_points.len());
         log::info!("Uncovered points: {}", self.data_points.len() - covered);
 
-        best.clusters.clone()
+        let initial_score = self.score(&initial_clusters);
+        let best_score = self.score(&best.clusters);
+        if best_score > initial_score {
+            log::info!(
+                "Keeping the initial solution, its score ({}) beats the optimized one ({})",
+                initial_score,
+                best_score
+            );
+            initial_clusters
+        } else {
+            best.clusters.clone()
+        }
+    }
+
+    /// Scores clusters the same way the final stats do, so the optimizer never hands back a worse route
+    fn score(&self, clusters: &SingleVec) -> usize {
+        let mut stats = Stats::new("Genetic".to_string(), self.min_points);
+        stats.set_weights(&self.data_points, &self.weights);
+        stats.set_radii(&self.data_points, &self.radii);
+        stats.cluster_stats(self.radius, &self.data_points, clusters);
+        stats.get_score()
     }
 
     /* -------------------------- core GA helpers -------------------------- */
@@ -128,6 +195,13 @@ impl GeneticClusterOptimizer {
 
         let mut population = Vec::with_capacity(self.population_size);
 
+        let candidates: Vec<[f64; 2]> = candidates::generate_clusters_from_points(
+            &self.data_points,
+            self.radius,
+            32,
+            self.seed,
+        );
+
         // Add the repaired initial solution
         population.push(Individual {
             clusters: seed.clone(),
@@ -164,10 +238,8 @@ impl GeneticClusterOptimizer {
                 }
             }
 
-            // Add a *small* random subset of candidates instead of all 256
-            let candidates: Vec<[f64; 2]> =
-                candidates::generate_clusters_from_points(&self.data_points, self.radius, 32);
-            for c in candidates {
+            // Add a *small* random subset of candidates instead of all of them
+            for c in candidates.iter().copied() {
                 if rng.random::<f64>() < 0.25 && clusters.len() < self.max_clusters {
                     let mut cc = c;
                     if rng.random::<f64>() < 0.4 {
@@ -249,7 +321,7 @@ impl GeneticClusterOptimizer {
             let useless = self.find_useless_clusters(&individual.clusters);
             if let Some(idx) = useless
                 .and_then(|v| if v.is_empty() { None } else { Some(v) })
-                .and_then(|v| Some(v[rng.random_range(0..v.len())]))
+                .map(|v| v[rng.random_range(0..v.len())])
             {
                 let _ = individual.clusters.remove(idx);
             } else {
@@ -429,12 +501,12 @@ impl GeneticClusterOptimizer {
         // 2) Enforce max_clusters (random prune; could be improved with marginal coverage)
         while clusters.len() > self.max_clusters {
             // Prefer dropping clusters that cover nothing
-            if let Some(useless) = self.find_useless_clusters(clusters) {
-                if !useless.is_empty() {
-                    let idx = useless[0];
-                    clusters.remove(idx);
-                    continue;
-                }
+            if let Some(useless) = self.find_useless_clusters(clusters)
+                && !useless.is_empty()
+            {
+                let idx = useless[0];
+                clusters.remove(idx);
+                continue;
             }
             clusters.pop();
         }
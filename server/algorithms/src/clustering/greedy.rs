@@ -1,3 +1,4 @@
+use geo::{Destination, Haversine};
 use geojson::{Feature, Geometry};
 use hashbrown::HashSet;
 use macros::time;
@@ -19,17 +20,28 @@ use crate::{
         candidates,
         rtree::{cluster::Cluster, point::Point},
     },
-    rtree::{self, SortDedupe},
+    exclusion::Exclusions,
+    progress::Progress,
+    rtree::{self, Radii, SortDedupe, TotalWeight, Weights},
     s2::{self, ToPointArray},
     utils,
 };
 
+const BYTE: usize = 1024;
+/// Buckets per unit of weight that weighted clusters are ranked by, so fractional weights aren't truncated
+const WEIGHT_BUCKETS: usize = 10;
+
 pub struct Greedy {
     cluster_mode: ClusterMode,
     cluster_split_level: u64,
     max_clusters: usize,
     min_points: usize,
     radius: Precision,
+    weights: Weights,
+    radii: Radii,
+    seed: u64,
+    progress: Progress,
+    exclusions: Exclusions,
 }
 
 impl Default for Greedy {
@@ -40,6 +52,11 @@ impl Default for Greedy {
             max_clusters: usize::MAX,
             min_points: 1,
             radius: 70.,
+            weights: Weights::default(),
+            radii: Radii::default(),
+            seed: rand::random(),
+            progress: Progress::default(),
+            exclusions: Exclusions::default(),
         }
     }
 }
@@ -65,6 +82,46 @@ impl<'a> Greedy {
         self.cluster_split_level = cluster_split_level;
         self
     }
+    /// When set, clusters are ranked by the weight they cover instead of their raw point count
+    /// and `min_points` is compared against that weight
+    pub fn set_weights(&mut self, weights: Weights) -> &mut Self {
+        self.weights = weights;
+        self
+    }
+    /// Per point radii, a cluster only covers a point that is within the point's own radius
+    pub fn set_radii(&mut self, radii: Radii) -> &mut Self {
+        self.radii = radii;
+        self
+    }
+    /// Seeds the jitter of the candidate grid
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+    /// Checked between the greedy iterations, a cancelled run returns the clusters it picked so far
+    pub fn set_progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) -> &mut Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Radius that clusters are spawned with, large enough to reach every point they may cover
+    fn cluster_radius(&self) -> Precision {
+        self.radii.max(self.radius)
+    }
+
+    /// Buckets per unit of weight, unweighted clusters only ever weigh whole numbers
+    fn buckets(&self) -> usize {
+        if self.weights.is_empty() {
+            1
+        } else {
+            WEIGHT_BUCKETS
+        }
+    }
 
     pub fn run(&'a self, points: &SingleVec) -> SingleVec {
         let time = Instant::now();
@@ -75,20 +132,23 @@ impl<'a> Greedy {
         } else {
             let cell_maps = s2::create_cell_map(&points, self.cluster_split_level);
 
-            let mut return_set = HashSet::new();
+            let mut cell_sets = vec![];
             std::thread::scope(|s| {
-                let handlers: Vec<std::thread::ScopedJoinHandle<'_, HashSet<Point>>> = cell_maps
-                    .iter()
-                    .map(|(key, values)| {
-                        log::debug!("Cell: {} | Points: {}", key, values.len());
-                        s.spawn(move || self.setup(values))
-                    })
-                    .collect();
+                let handlers: Vec<(u64, std::thread::ScopedJoinHandle<'_, HashSet<Point>>)> =
+                    cell_maps
+                        .iter()
+                        .map(|(key, values)| {
+                            log::debug!("Cell: {} | Points: {}", key, values.len());
+                            (*key, s.spawn(move || self.setup(values)))
+                        })
+                        .collect();
                 log::info!("created {} threads", handlers.len());
-                for thread in handlers {
+                let total = handlers.len();
+                for (index, (key, thread)) in handlers.into_iter().enumerate() {
+                    self.progress.set_steps(index, total);
                     match thread.join() {
                         Ok(results) => {
-                            return_set.extend(results);
+                            cell_sets.push((key, results));
                         }
                         Err(e) => {
                             log::error!("error joining thread: {:?}", e)
@@ -97,13 +157,171 @@ impl<'a> Greedy {
                 }
             });
 
-            return_set
+            if self.progress.is_cancelled() {
+                cell_sets.into_iter().flat_map(|(_, set)| set).collect()
+            } else {
+                self.reconcile_seams(points, cell_sets)
+            }
         };
 
         log::info!("finished in {:.2}s", time.elapsed().as_secs_f32());
         return_set.into_iter().map(|p| p.center).collect()
     }
 
+    /// True when anything within `distance` of the point lies outside of the given split cell
+    fn near_seam(&self, point: &[Precision; 2], cell: u64, distance: Precision) -> bool {
+        let center = geo::Point::new(point[1], point[0]);
+        std::iter::once(center)
+            .chain((0..8).map(|i| Haversine.destination(center, i as Precision * 45., distance)))
+            .any(|edge| {
+                s2::from_array_to_cell_id(&[edge.y(), edge.x()], self.cluster_split_level).0 != cell
+            })
+    }
+
+    /// Each split cell is clustered without knowing about its neighbors, which leaves duplicated
+    /// or redundant clusters along the cell borders.
+    /// The data points near the borders that no interior cluster covers are clustered again as one group, and any
+    /// cluster that covers less than `min_points` worth of points no other cluster covers is dropped
+    fn reconcile_seams(
+        &'a self,
+        points: &SingleVec,
+        cell_sets: Vec<(u64, HashSet<Point>)>,
+    ) -> HashSet<Point> {
+        let time = Instant::now();
+        let point_tree: RTree<Point> =
+            rtree::spawn_with(self.radius, points, &self.weights, &self.radii);
+        let key = |point: &Point| point.center.map(Precision::to_bits);
+
+        let mut seam = vec![];
+        let mut clusters = vec![];
+        for (cell, set) in cell_sets {
+            for cluster in set {
+                if self.near_seam(&cluster.center, cell, self.cluster_radius()) {
+                    seam.push(cluster);
+                } else {
+                    clusters.push(cluster);
+                }
+            }
+        }
+        // the cells and their sets come in no particular order, ties below are broken by position
+        let by_center = |a: &Point, b: &Point| {
+            a.center[0]
+                .total_cmp(&b.center[0])
+                .then_with(|| a.center[1].total_cmp(&b.center[1]))
+        };
+        seam.sort_by(by_center);
+        clusters.sort_by(by_center);
+        let fixed: HashSet<[u64; 2]> = clusters
+            .iter()
+            .flat_map(|cluster| point_tree.locate_all_at_point(&cluster.center))
+            .map(key)
+            .collect();
+        // a cluster two radii away from the border can still cover a point next to it
+        let strip: Vec<&Point> = point_tree
+            .iter()
+            .filter(|point| !fixed.contains(&key(point)))
+            .filter(|point| {
+                let cell = s2::from_array_to_cell_id(&point.center, self.cluster_split_level).0;
+                self.near_seam(&point.center, cell, self.cluster_radius() * 2.)
+            })
+            .collect();
+        let strip_points: SingleVec = strip.iter().map(|point| point.center).collect();
+
+        // local mygod score of a set of clusters over the seam points
+        let score = |candidates: &[Point]| {
+            let covered: HashSet<[u64; 2]> = candidates
+                .iter()
+                .flat_map(|cluster| point_tree.locate_all_at_point(&cluster.center))
+                .map(key)
+                .collect();
+            candidates.len() as Precision * self.min_points as Precision
+                + strip
+                    .iter()
+                    .filter(|point| !covered.contains(&key(point)))
+                    .map(|point| point.weight)
+                    .sum::<Precision>()
+        };
+        let mut reclustered: Vec<Point> = if strip_points.is_empty() {
+            vec![]
+        } else {
+            self.setup(&strip_points).into_iter().collect()
+        };
+        reclustered.sort_by(by_center);
+        let seam_count = seam.len();
+        let seam = if score(&reclustered) < score(&seam) {
+            reclustered
+        } else {
+            seam
+        };
+        log::info!(
+            "reclustered {} seam clusters into {}",
+            seam_count,
+            seam.len()
+        );
+
+        // anything touching the seam may have been made redundant by a neighbor
+        let seam_points: HashSet<[u64; 2]> = seam
+            .iter()
+            .flat_map(|cluster| point_tree.locate_all_at_point(&cluster.center))
+            .map(key)
+            .collect();
+        clusters.extend(seam);
+
+        let covers: Vec<Vec<&Point>> = clusters
+            .iter()
+            .map(|cluster| point_tree.locate_all_at_point(&cluster.center).collect())
+            .collect();
+        // points at the same coordinates are covered by the same clusters, so they're counted once per cluster
+        let cover_keys: Vec<HashSet<[u64; 2]>> = covers
+            .iter()
+            .map(|cover| cover.iter().map(|point| key(point)).collect())
+            .collect();
+        let mut coverage: hashbrown::HashMap<[u64; 2], usize> = hashbrown::HashMap::new();
+        for point in cover_keys.iter().flatten() {
+            *coverage.entry(*point).or_insert(0) += 1;
+        }
+        let unique_weight = |coverage: &hashbrown::HashMap<[u64; 2], usize>, index: usize| {
+            covers[index]
+                .iter()
+                .filter(|point| coverage.get(&key(point)) == Some(&1))
+                .map(|point| point.weight)
+                .sum::<Precision>()
+        };
+        let mut candidates: Vec<usize> = (0..clusters.len())
+            .filter(|&index| {
+                covers[index]
+                    .iter()
+                    .any(|point| seam_points.contains(&key(point)))
+            })
+            .collect();
+        candidates
+            .sort_by(|a, b| unique_weight(&coverage, *a).total_cmp(&unique_weight(&coverage, *b)));
+
+        let mut removed = vec![false; clusters.len()];
+        for index in candidates {
+            if unique_weight(&coverage, index) < self.min_points as Precision {
+                for point in cover_keys[index].iter() {
+                    if let Some(count) = coverage.get_mut(point) {
+                        *count -= 1;
+                    }
+                }
+                removed[index] = true;
+            }
+        }
+        log::info!(
+            "removed {} redundant seam clusters in {:.2}s",
+            removed.iter().filter(|removed| **removed).count(),
+            time.elapsed().as_secs_f32()
+        );
+
+        clusters
+            .into_iter()
+            .zip(removed)
+            .filter(|(_, removed)| !removed)
+            .map(|(cluster, _)| cluster)
+            .collect()
+    }
+
     fn get_honeycomb_clusters(&self, points: &SingleVec) -> SingleVec {
         let bbox = points.get_bbox();
         let bbox_unwrap = bbox.clone().unwrap();
@@ -123,7 +341,7 @@ impl<'a> Greedy {
             }),
             ..Default::default()
         };
-        radius::BootstrapRadius::new(&feat, self.radius).result()
+        radius::BootstrapRadius::new(&feat, self.radii.min(self.radius)).result()
     }
 
     fn flat_map_cells(&self, cell: CellID, point_tree: &'a RTree<Point>) -> Vec<CellID> {
@@ -151,21 +369,12 @@ impl<'a> Greedy {
     }
 
     fn gen_clusters(&self, density: usize, points: &'a SingleVec) -> SingleVec {
-        candidates::generate_clusters_from_points(points, self.radius, density)
+        candidates::generate_clusters_from_points(points, self.cluster_radius(), density, self.seed)
     }
 
-    fn associate_clusters(
-        &'a self,
-        points: &'a SingleVec,
-        point_tree: &'a RTree<Point>,
-    ) -> Vec<Vec<Cluster<'a>>> {
-        const BYTE: usize = 1024;
-
-        let sys = System::new_all();
-        let sys_mem = sys.available_memory() as usize / BYTE / BYTE;
-
-        let time = Instant::now();
-        let clusters_with_data: Vec<Cluster> = match self.cluster_mode {
+    /// Candidate cluster centers for the selected cluster mode, none of them inside of an exclusion
+    pub fn candidates(&'a self, points: &'a SingleVec, point_tree: &'a RTree<Point>) -> SingleVec {
+        let candidates = match self.cluster_mode {
             ClusterMode::Honeycomb => self.get_honeycomb_clusters(points),
             ClusterMode::Fast => self.gen_clusters(BYTE / 2, points),
             ClusterMode::Balanced => self.gen_clusters(BYTE, points),
@@ -178,17 +387,37 @@ impl<'a> Greedy {
                 pcs
             }
             _ => vec![],
-        }
-        .into_par_iter()
-        .filter_map(|cluster| {
-            let iter = point_tree.locate_all_at_point(&cluster);
-            let mut points = Vec::with_capacity(iter.size_hint().0);
-            points.extend(iter);
+        };
+        self.exclusions
+            .relocate_all(candidates, self.cluster_radius())
+    }
+
+    fn associate_clusters(
+        &'a self,
+        points: &'a SingleVec,
+        point_tree: &'a RTree<Point>,
+    ) -> Vec<Vec<Cluster<'a>>> {
+        let sys = System::new_all();
+        let sys_mem = sys.available_memory() as usize / BYTE / BYTE;
 
-            (points.len() >= self.min_points)
-                .then(|| Cluster::new(Point::new(self.radius, 20, cluster), points, vec![]))
-        })
-        .collect();
+        let time = Instant::now();
+        let clusters_with_data: Vec<Cluster> = self
+            .candidates(points, point_tree)
+            .into_par_iter()
+            .filter_map(|cluster| {
+                let iter = point_tree.locate_all_at_point(&cluster);
+                let mut points = Vec::with_capacity(iter.size_hint().0);
+                points.extend(iter);
+
+                (points.total_weight() >= self.min_points as Precision).then(|| {
+                    Cluster::new(
+                        Point::new(self.cluster_radius(), 20, cluster),
+                        points,
+                        vec![],
+                    )
+                })
+            })
+            .collect();
 
         log::info!(
             "associated points with {} clusters in {:.2}s",
@@ -216,11 +445,10 @@ impl<'a> Greedy {
         }
 
         let time = Instant::now();
-        let max = clusters_with_data
-            .iter()
-            .map(|cluster| cluster.all.len())
-            .max()
-            .unwrap_or(100);
+        let bucket = |cluster: &Cluster| {
+            (cluster.all.total_weight() * self.buckets() as Precision).floor() as usize
+        };
+        let max = clusters_with_data.iter().map(bucket).max().unwrap_or(100);
         log::info!(
             "found best cluster ({}) {:.2}s",
             max,
@@ -231,7 +459,7 @@ impl<'a> Greedy {
         let mut clustered_clusters = vec![vec![]; max + 1];
 
         for cluster in clusters_with_data.into_iter() {
-            clustered_clusters[cluster.all.len()].push(cluster);
+            clustered_clusters[bucket(&cluster)].push(cluster);
         }
         log::info!(
             "sorted clusters by size in {:.2}s",
@@ -243,10 +471,14 @@ impl<'a> Greedy {
 
     fn setup(&'a self, points: &SingleVec) -> HashSet<Point> {
         let time = Instant::now();
-        let point_tree: RTree<Point> = rtree::spawn(self.radius, points);
+        let point_tree: RTree<Point> =
+            rtree::spawn_with(self.radius, points, &self.weights, &self.radii);
         log::info!("created point tree in {:.2}s", time.elapsed().as_secs_f32());
 
         let clusters_with_data = self.associate_clusters(points, &point_tree);
+        if self.progress.is_cancelled() {
+            return HashSet::new();
+        }
 
         let mut solution = self.cluster(&clusters_with_data).into_iter().collect();
 
@@ -264,8 +496,11 @@ impl<'a> Greedy {
         let mut new_clusters = HashSet::<Cluster>::new();
         let mut blocked_points = HashSet::<&Point>::new();
 
+        // clusters are bucketed by their weight times [Greedy::buckets]
+        let buckets = self.buckets();
+        let min_bucket = self.min_points * buckets;
         let mut current = clusters_with_data.len() - 1;
-        let total_iterations = current - self.min_points + 1;
+        let total_iterations = current.saturating_sub(min_bucket) + 1;
         let mut current_iteration = 0;
         let mut stdout = std::io::stdout();
 
@@ -277,8 +512,13 @@ impl<'a> Greedy {
         let capacity = clusters_with_data.iter().map(|c| c.len()).sum::<usize>();
         let mut clusters_of_interest: Vec<&Cluster<'_>> = Vec::with_capacity(capacity);
 
-        'greedy: while current >= self.min_points && new_clusters.len() < self.max_clusters {
+        'greedy: while current >= min_bucket && new_clusters.len() < self.max_clusters {
+            if self.progress.is_cancelled() {
+                log::warn!("cancelled with {} clusters", new_clusters.len());
+                break;
+            }
             current_iteration += 1;
+            let threshold = current as Precision / buckets as Precision;
             let time = Instant::now();
             clusters_of_interest.clear();
             for (index, clusters) in clusters_with_data.iter().enumerate() {
@@ -304,7 +544,7 @@ impl<'a> Greedy {
                             }
                         })
                         .collect();
-                    if points.len() < current {
+                    if points.total_weight() < threshold {
                         None
                     } else {
                         points.sort_dedupe();
@@ -326,10 +566,11 @@ impl<'a> Greedy {
 
             let time = Instant::now();
             local_clusters.par_sort_by(|a, b| {
-                if a.unique.len() == b.unique.len() {
-                    b.all.len().cmp(&a.all.len())
+                let (a_unique, b_unique) = (a.unique.total_weight(), b.unique.total_weight());
+                if a_unique == b_unique {
+                    b.all.total_weight().total_cmp(&a.all.total_weight())
                 } else {
-                    b.unique.len().cmp(&a.unique.len())
+                    b_unique.total_cmp(&a_unique)
                 }
             });
             sorting_time += time.elapsed().as_secs_f32();
@@ -339,7 +580,7 @@ impl<'a> Greedy {
                 if new_clusters.len() >= self.max_clusters {
                     break 'greedy;
                 }
-                if cluster.unique.len() >= current {
+                if cluster.unique.total_weight() >= threshold {
                     for point in cluster.unique.iter() {
                         if blocked_points.contains(point) {
                             continue 'cluster;
@@ -354,7 +595,11 @@ impl<'a> Greedy {
             iterating_local_time += time.elapsed().as_secs_f32();
 
             let time = Instant::now();
-            if current >= self.min_points {
+            // split cells run in parallel, their progress is counted per cell in `run` instead
+            if self.cluster_split_level == 0 {
+                self.progress.set_steps(current_iteration, total_iterations);
+            }
+            if current >= min_bucket {
                 stdout
                     .write(
                         utils::info_log(
@@ -390,7 +635,7 @@ impl<'a> Greedy {
     #[time()]
     fn update_unique(&'a self, clusters: &mut Vec<Cluster>) {
         let cluster_tree = rtree::spawn(
-            self.radius,
+            self.cluster_radius(),
             &clusters.iter().map(|c| c.point.center).collect(),
         );
 
@@ -398,7 +643,7 @@ impl<'a> Greedy {
             .par_iter_mut()
             .for_each(|cluster| cluster.set_unique(&cluster_tree));
 
-        clusters.retain(|cluster| cluster.unique.len() >= self.min_points);
+        clusters.retain(|cluster| cluster.unique.total_weight() >= self.min_points as Precision);
 
         log::info!("unique solution size: {}", clusters.len());
 
@@ -424,7 +669,9 @@ impl<'a> Greedy {
                         if seen_cell_ids.contains(&cell_id) {
                             None
                         } else {
-                            Some(Point::new(self.radius, 20, *p))
+                            // a point inside of an exclusion is covered from just outside of it, if it's close enough
+                            let center = self.exclusions.relocate(*p, self.radius)?;
+                            Some(Point::new(self.radius, 20, center))
                         }
                     })
                     .collect::<Vec<Point>>()
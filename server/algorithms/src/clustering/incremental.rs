@@ -0,0 +1,170 @@
+use geo::{Distance, Haversine, Point};
+use hashbrown::HashSet;
+use model::api::{Precision, single_vec::SingleVec};
+use rstar::RTree;
+
+use crate::rtree::{self, Radii, TotalWeight, Weights, point};
+
+/// Clusters closer than this (in degrees) count as the same cluster when comparing routes
+const SAME_CLUSTER: Precision = 1e-6;
+
+pub struct Split {
+    /// Existing clusters that stay in the route, in their original order
+    pub kept: SingleVec,
+    /// Existing clusters that no longer cover `min_points`
+    pub removed: SingleVec,
+    /// Indices of the data points that no kept cluster covers
+    pub uncovered: Vec<usize>,
+}
+
+impl Split {
+    /// Picks the values that belong to the uncovered data points, empty stays empty
+    pub fn uncovered<T: Copy>(&self, values: &[T]) -> Vec<T> {
+        if values.is_empty() {
+            vec![]
+        } else {
+            self.uncovered.iter().map(|&index| values[index]).collect()
+        }
+    }
+}
+
+/// Treats `existing` as fixed clusters and finds the data points they leave uncovered.
+/// With `drop_underused`, clusters covering less than `min_points` worth of data points are removed first
+pub fn split(
+    existing: &SingleVec,
+    data_points: &SingleVec,
+    weights: &[Precision],
+    radii: &[Precision],
+    radius: Precision,
+    min_points: usize,
+    drop_underused: bool,
+) -> Split {
+    let point_tree = rtree::spawn_with(
+        radius,
+        data_points,
+        &Weights::new(data_points, weights),
+        &Radii::new(data_points, radii),
+    );
+
+    let (kept, removed): (SingleVec, SingleVec) = existing.iter().partition(|cluster| {
+        !drop_underused
+            || point_tree
+                .locate_all_at_point(cluster)
+                .collect::<Vec<_>>()
+                .total_weight()
+                >= min_points as Precision
+    });
+
+    let covered = covered(&point_tree, &kept);
+    let uncovered: Vec<usize> = data_points
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| !covered.contains(&point.map(Precision::to_bits)))
+        .map(|(index, _)| index)
+        .collect();
+
+    log::info!(
+        "kept {} of {} existing clusters, {} data points are left uncovered",
+        kept.len(),
+        existing.len(),
+        uncovered.len()
+    );
+
+    Split {
+        kept,
+        removed,
+        uncovered,
+    }
+}
+
+/// Data points that sit inside of at least one of the clusters
+fn covered(point_tree: &RTree<point::Point>, clusters: &SingleVec) -> HashSet<[u64; 2]> {
+    clusters
+        .iter()
+        .flat_map(|cluster| point_tree.locate_all_at_point(cluster))
+        .map(|point| point.center.map(Precision::to_bits))
+        .collect()
+}
+
+pub struct Diff {
+    /// Clusters of the second route that the first one doesn't have
+    pub added: SingleVec,
+    /// Clusters of the first route that the second one doesn't have
+    pub removed: SingleVec,
+    /// Data points that only the first route covers
+    pub only_first: SingleVec,
+    /// Data points that only the second route covers
+    pub only_second: SingleVec,
+}
+
+/// Compares two routes by their clusters and by the data points they cover
+pub fn diff(
+    first: &SingleVec,
+    second: &SingleVec,
+    data_points: &SingleVec,
+    radii: &[Precision],
+    radius: Precision,
+) -> Diff {
+    let key = |cluster: &[Precision; 2]| cluster.map(|c| (c / SAME_CLUSTER).round() as i64);
+    let first_keys: HashSet<[i64; 2]> = first.iter().map(key).collect();
+    let second_keys: HashSet<[i64; 2]> = second.iter().map(key).collect();
+
+    let point_tree = rtree::spawn_with(
+        radius,
+        data_points,
+        &Weights::default(),
+        &Radii::new(data_points, radii),
+    );
+    let first_covered = covered(&point_tree, first);
+    let second_covered = covered(&point_tree, second);
+    let only = |covered: &HashSet<[u64; 2]>, other: &HashSet<[u64; 2]>| {
+        data_points
+            .iter()
+            .filter(|point| {
+                let point = point.map(Precision::to_bits);
+                covered.contains(&point) && !other.contains(&point)
+            })
+            .copied()
+            .collect()
+    };
+
+    Diff {
+        added: second
+            .iter()
+            .filter(|cluster| !first_keys.contains(&key(cluster)))
+            .copied()
+            .collect(),
+        removed: first
+            .iter()
+            .filter(|cluster| !second_keys.contains(&key(cluster)))
+            .copied()
+            .collect(),
+        only_first: only(&first_covered, &second_covered),
+        only_second: only(&second_covered, &first_covered),
+    }
+}
+
+fn distance(a: &[Precision; 2], b: &[Precision; 2]) -> Precision {
+    Haversine.distance(Point::new(a[1], a[0]), Point::new(b[1], b[0]))
+}
+
+/// Inserts each added cluster where it lengthens the route the least, the kept clusters keep their order
+pub fn merge(kept: SingleVec, added: &SingleVec) -> SingleVec {
+    let mut route = kept;
+    for cluster in added.iter() {
+        if route.len() < 2 {
+            route.push(*cluster);
+            continue;
+        }
+        let detour = |i: usize| {
+            let a = &route[i];
+            let b = &route[(i + 1) % route.len()];
+            distance(a, cluster) + distance(cluster, b) - distance(a, b)
+        };
+        let index = (0..route.len())
+            .min_by(|a, b| detour(*a).total_cmp(&detour(*b)))
+            .unwrap_or(0);
+        route.insert(index + 1, *cluster);
+    }
+    route
+}
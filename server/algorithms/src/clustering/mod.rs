@@ -1,109 +1,172 @@
-use std::{time::Instant, vec};
+use std::{collections::BTreeMap, time::Instant, vec};
 
 use crate::{
-    plugin::{Folder, JoinFunction, Plugin},
+    plugin::{Folder, JoinFunction, Plugin, discovery, manifest::Manifest},
     stats::Stats,
-    utils,
+    strategy::{self, ClusterInput},
 };
 
-use self::greedy::Greedy;
-
 use super::*;
 
-use geojson::FeatureCollection;
 use model::api::{calc_mode::CalculationMode, cluster_mode::ClusterMode, single_vec::SingleVec};
 
 mod candidates;
+mod coverage;
+mod exact;
 mod fastest;
-// mod genetic;
+mod genetic;
 mod greedy;
+pub mod incremental;
 mod s2;
+pub(crate) mod strategies;
 
-pub fn main(
-    data_points: &SingleVec,
-    cluster_mode: ClusterMode,
-    radius: f64,
-    min_points: usize,
-    stats: &mut Stats,
-    cluster_split_level: u64,
-    max_clusters: usize,
-    calculation_mode: CalculationMode,
-    s2_level: u8,
-    s2_size: u8,
-    collection: FeatureCollection,
-    clustering_args: &str,
-    center_clusters: bool,
-    _genetic_post_processing: bool,
-) -> SingleVec {
+/// Clusters the data points with the strategy the input picks, then centers, post processes and
+/// completes the coverage of the clusters as asked
+pub fn main(input: &ClusterInput, stats: &mut Stats) -> SingleVec {
+    let ClusterInput {
+        data_points,
+        weights,
+        radii,
+        radius,
+        min_points,
+        max_clusters,
+        ref cluster_mode,
+        cluster_split_level,
+        ref calculation_mode,
+        area,
+        args: clustering_args,
+        center_clusters,
+        genetic_post_processing,
+        genetic_time_budget,
+        exact_max_points,
+        min_coverage,
+        seed,
+        exclusions,
+        progress,
+        ..
+    } = *input;
     if data_points.is_empty() {
         return vec![];
     }
     let time = Instant::now();
-    let clusters = match calculation_mode {
-        CalculationMode::S2 => collection
-            .into_iter()
-            .flat_map(|feature| s2::cluster(feature, data_points, s2_level, s2_size, min_points))
-            .collect(),
-        _ => match cluster_mode {
-            ClusterMode::Fastest => {
-                let clusters = fastest::main(&data_points, radius, min_points);
-                clusters
-            }
-            ClusterMode::Honeycomb
-            | ClusterMode::Balanced
-            | ClusterMode::Fast
-            | ClusterMode::Better
-            | ClusterMode::Best => {
-                let mut greedy = Greedy::default();
-                greedy
-                    .set_cluster_mode(cluster_mode)
-                    .set_cluster_split_level(cluster_split_level)
-                    .set_max_clusters(max_clusters)
-                    .set_min_points(min_points)
-                    .set_radius(radius);
-
-                greedy.run(&data_points)
-            }
-            ClusterMode::Custom(plugin) => {
-                match Plugin::new(
-                    &plugin,
-                    Folder::Clustering,
-                    cluster_split_level,
-                    clustering_args,
-                ) {
-                    Ok(plugin_manager) => {
-                        match plugin_manager.run_multi::<JoinFunction>(data_points, None) {
-                            Ok(sorted_clusters) => sorted_clusters,
-                            Err(e) => {
-                                log::error!("Error while running plugin: {}", e);
-                                vec![]
-                            }
-                        }
-                    }
+    progress.set_phase("clustering");
+    stats.set_weights(data_points, weights);
+    stats.set_radii(data_points, radii);
+    let point_weights = input.point_weights();
+    let point_radii = input.point_radii();
+    // a cluster placed within the smallest radius covers every point it is placed for
+    let min_radius = point_radii.min(radius);
+    let cluster_mode = if *cluster_mode == ClusterMode::Exact
+        && data_points.len() > exact_max_points
+    {
+        log::warn!(
+            "exact clustering supports up to {} data points, {} were provided, using `best` strategy instead",
+            exact_max_points,
+            data_points.len()
+        );
+        ClusterMode::Best
+    } else {
+        cluster_mode.clone()
+    };
+    let strategy_name = match calculation_mode {
+        CalculationMode::S2 => "s2".to_string(),
+        _ => cluster_mode.to_string(),
+    };
+    let clusters = match strategy::CLUSTERING.get(&strategy_name) {
+        Some(strategy) => strategy.cluster(input, stats),
+        None => match Plugin::new(
+            &strategy_name,
+            Folder::Clustering,
+            cluster_split_level,
+            clustering_args,
+        ) {
+            Ok(mut plugin_manager) => {
+                plugin_manager
+                    .set_radius(radius)
+                    .set_weights(point_weights.clone())
+                    .set_area(area.clone())
+                    .set_progress(progress.clone());
+                let clusters = match plugin_manager.run_multi::<JoinFunction>(data_points, None) {
+                    Ok(sorted_clusters) => sorted_clusters,
                     Err(e) => {
-                        log::error!("Plugin not found: {}", e);
+                        log::error!("Error while running plugin: {}", e);
                         vec![]
                     }
-                }
+                };
+                stats.add_plugin_report(plugin_manager.report());
+                clusters
+            }
+            Err(e) => {
+                log::error!("Plugin not found: {}", e);
+                vec![]
             }
         },
     };
+    if progress.is_cancelled() {
+        log::warn!("clustering was cancelled");
+        return clusters;
+    }
+    // the modes collect into hash sets, a fixed order keeps the steps below reproducible
+    let mut clusters = clusters;
+    clusters.sort_by(|a, b| a[0].total_cmp(&b[0]).then_with(|| a[1].total_cmp(&b[1])));
+    let solved = clusters.clone();
     let clusters = if center_clusters {
-        sec::with_data(radius, data_points, &clusters)
+        progress.set_phase("centering");
+        sec::with_data(min_radius, data_points, &clusters, seed, exclusions)
     } else {
         clusters
     };
-    // let clusters = if genetic_post_processing {
-    //     let optimizer = genetic::GeneticClusterOptimizer::new(
-    //         data_points.clone(),
-    //         min_points,
-    //         max_clusters,
-    //         radius,
-    //     );
-    //     optimizer.optimize(clusters)
-    // } else {
-    //     clusters
-    // };
+    let clusters = if genetic_post_processing {
+        progress.set_phase("genetic");
+        let mut optimizer = genetic::GeneticClusterOptimizer::new(
+            data_points.clone(),
+            min_points,
+            max_clusters,
+            radius,
+        );
+        optimizer
+            .set_time_budget(genetic_time_budget)
+            .set_seed(seed)
+            .set_progress(progress.clone())
+            .set_weights(weights.to_vec())
+            .set_radii(radii.to_vec());
+        optimizer.optimize(clusters)
+    } else {
+        clusters
+    };
+    if progress.is_cancelled() {
+        log::warn!("clustering was cancelled");
+        return clusters;
+    }
+    // the other strategies, plugins and the post processing don't know about exclusions
+    let clusters = coverage::relocate(
+        data_points,
+        clusters,
+        radius,
+        max_clusters,
+        &point_weights,
+        &point_radii,
+        seed,
+        exclusions,
+    );
+    let clusters = coverage::fill(
+        data_points,
+        clusters,
+        radius,
+        min_coverage,
+        max_clusters,
+        &point_weights,
+        &point_radii,
+        seed,
+        exclusions,
+    );
+
+    // the lower bound of the strategy only holds for the clusters it returned
+    let mut result = clusters.clone();
+    result.sort_by(|a, b| a[0].total_cmp(&b[0]).then_with(|| a[1].total_cmp(&b[1])));
+    if result != solved {
+        stats.set_lower_bound(0.);
+    }
 
     stats.set_cluster_time(time);
     stats.cluster_stats(radius, data_points, &clusters);
@@ -113,16 +176,22 @@ pub fn main(
 }
 
 pub fn clustering_plugins() -> Vec<String> {
-    utils::get_plugin_list("algorithms/src/clustering/plugins").unwrap_or(vec![])
+    discovery::plugins(Folder::Clustering)
+}
+
+pub fn clustering_plugin_manifests() -> BTreeMap<String, Manifest> {
+    Manifest::all(Folder::Clustering)
+}
+
+/// Registered strategies that don't ship with Kōji, followed by the plugins
+pub fn custom_clustering_options() -> Vec<String> {
+    let mut options = strategy::CLUSTERING.custom_names();
+    options.extend(clustering_plugins());
+    options
 }
 
 pub fn all_clustering_options() -> Vec<String> {
-    let mut options = clustering_plugins();
-    options.push("honeycomb".to_string());
-    options.push("fastest".to_string());
-    options.push("balanced".to_string());
-    options.push("fast".to_string());
-    options.push("better".to_string());
-    options.push("best".to_string());
+    let mut options = custom_clustering_options();
+    options.extend(strategy::CLUSTERING.builtin_names());
     options
 }
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use model::api::{cluster_mode::ClusterMode, single_vec::SingleVec};
+
+use super::{exact::Exact, fastest, greedy::Greedy, s2};
+use crate::{
+    stats::Stats,
+    strategy::{ClusterInput, ClusterStrategy},
+};
+
+pub(crate) fn builtins() -> Vec<(&'static str, Arc<dyn ClusterStrategy>)> {
+    vec![
+        (
+            "honeycomb",
+            Arc::new(GreedyStrategy(ClusterMode::Honeycomb)),
+        ),
+        ("fastest", Arc::new(FastestStrategy)),
+        ("balanced", Arc::new(GreedyStrategy(ClusterMode::Balanced))),
+        ("fast", Arc::new(GreedyStrategy(ClusterMode::Fast))),
+        ("better", Arc::new(GreedyStrategy(ClusterMode::Better))),
+        ("best", Arc::new(GreedyStrategy(ClusterMode::Best))),
+        ("exact", Arc::new(ExactStrategy)),
+        ("s2", Arc::new(S2Strategy)),
+    ]
+}
+
+struct GreedyStrategy(ClusterMode);
+
+impl ClusterStrategy for GreedyStrategy {
+    fn cluster(&self, input: &ClusterInput, _stats: &mut Stats) -> SingleVec {
+        let mut greedy = Greedy::default();
+        greedy
+            .set_cluster_mode(self.0.clone())
+            .set_cluster_split_level(input.cluster_split_level)
+            .set_max_clusters(input.max_clusters)
+            .set_min_points(input.min_points)
+            .set_radius(input.radius)
+            .set_weights(input.point_weights())
+            .set_radii(input.point_radii())
+            .set_seed(input.seed)
+            .set_progress(input.progress.clone())
+            .set_exclusions(input.exclusions.clone());
+
+        greedy.run(input.data_points)
+    }
+}
+
+struct FastestStrategy;
+
+impl ClusterStrategy for FastestStrategy {
+    fn cluster(&self, input: &ClusterInput, _stats: &mut Stats) -> SingleVec {
+        // a cluster placed within the smallest radius covers every point it is placed for
+        let min_radius = input.point_radii().min(input.radius);
+        fastest::main(
+            input.data_points,
+            min_radius,
+            input.min_points,
+            &input.point_weights(),
+        )
+    }
+}
+
+struct ExactStrategy;
+
+impl ClusterStrategy for ExactStrategy {
+    fn cluster(&self, input: &ClusterInput, stats: &mut Stats) -> SingleVec {
+        let solution = Exact::default()
+            .set_radius(input.radius)
+            .set_min_points(input.min_points)
+            .set_max_clusters(input.max_clusters)
+            .set_weights(input.point_weights())
+            .set_radii(input.point_radii())
+            .set_seed(input.seed)
+            .set_time_budget(input.exact_time_budget)
+            .set_progress(input.progress.clone())
+            .run(input.data_points);
+        stats.set_lower_bound(solution.lower_bound);
+        solution.clusters
+    }
+}
+
+/// Looked up by `CalculationMode::S2` rather than by the cluster mode
+struct S2Strategy;
+
+impl ClusterStrategy for S2Strategy {
+    fn cluster(&self, input: &ClusterInput, _stats: &mut Stats) -> SingleVec {
+        input
+            .area
+            .features
+            .iter()
+            .flat_map(|feature| {
+                s2::cluster(
+                    feature.clone(),
+                    input.data_points,
+                    input.s2_level,
+                    input.s2_size,
+                    input.min_points,
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,107 @@
+use geo::{
+    Bearing, BoundingRect, Closest, ClosestPoint, Contains, Destination, Distance, Haversine,
+    Intersects, MultiPolygon, Point, Polygon, Rect,
+};
+use geojson::{FeatureCollection, Value};
+use model::api::{Precision, point_array::PointArray, single_vec::SingleVec};
+
+/// Meters a center that was moved out of an exclusion keeps from its edge
+const MARGIN: Precision = 1.;
+/// Times a moved center may land in another exclusion before it's dropped
+const MAX_MOVES: usize = 4;
+
+/// Areas that no cluster or bootstrap circle may have its center in, like lakes or airports
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions(Vec<(Polygon, Rect)>);
+
+impl Exclusions {
+    /// The polygons and multi polygons of the collection, other geometries are ignored
+    pub fn new(collection: &FeatureCollection) -> Self {
+        let mut polygons = vec![];
+        for geometry in collection
+            .features
+            .iter()
+            .filter_map(|f| f.geometry.as_ref())
+        {
+            match geometry.value {
+                Value::Polygon(_) => polygons.extend(Polygon::try_from(geometry).ok()),
+                Value::MultiPolygon(_) => polygons.extend(
+                    MultiPolygon::try_from(geometry)
+                        .map(|multi| multi.0)
+                        .unwrap_or_default(),
+                ),
+                _ => log::warn!("ignoring exclusion that is not a polygon"),
+            }
+        }
+        Self(
+            polygons
+                .into_iter()
+                .filter_map(|polygon| {
+                    let bounds = polygon.bounding_rect()?;
+                    Some((polygon, bounds))
+                })
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, point: &PointArray) -> bool {
+        self.find(Point::new(point[1], point[0])).is_some()
+    }
+
+    fn find(&self, point: Point) -> Option<&Polygon> {
+        self.0
+            .iter()
+            .find(|(polygon, bounds)| bounds.intersects(&point) && polygon.contains(&point))
+            .map(|(polygon, _)| polygon)
+    }
+
+    /// The center itself when it's outside of every exclusion, otherwise the closest spot just outside of the
+    /// exclusion, so a circle there still covers what the original one covered near the edge.
+    /// `None` when that spot is further than `radius` away, the circle then only reached into exclusions
+    pub fn relocate(&self, center: PointArray, radius: Precision) -> Option<PointArray> {
+        let origin = Point::new(center[1], center[0]);
+        let mut current = origin;
+        for _ in 0..MAX_MOVES {
+            let Some(polygon) = self.find(current) else {
+                return (Haversine.distance(origin, current) <= radius)
+                    .then_some([current.y(), current.x()]);
+            };
+            let edge = std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .filter_map(|ring| match ring.closest_point(&current) {
+                    Closest::Intersection(point) | Closest::SinglePoint(point) => Some(point),
+                    Closest::Indeterminate => None,
+                })
+                .min_by(|a, b| {
+                    Haversine
+                        .distance(current, *a)
+                        .total_cmp(&Haversine.distance(current, *b))
+                })?;
+            current = Haversine.destination(edge, Haversine.bearing(current, edge), MARGIN);
+        }
+        None
+    }
+
+    /// Relocates every center that's inside of an exclusion, dropping those that can't be
+    pub fn relocate_all(&self, centers: SingleVec, radius: Precision) -> SingleVec {
+        if self.is_empty() {
+            return centers;
+        }
+        let total = centers.len();
+        let centers: SingleVec = centers
+            .into_iter()
+            .filter_map(|center| self.relocate(center, radius))
+            .collect();
+        if centers.len() < total {
+            log::info!(
+                "dropped {} centers that only reached into exclusions",
+                total - centers.len()
+            );
+        }
+        centers
+    }
+}
@@ -2,11 +2,14 @@ use model;
 
 pub mod bootstrap;
 pub mod clustering;
-mod plugin;
+pub mod exclusion;
+pub mod plugin;
+pub mod progress;
 mod project;
 pub mod routing;
 mod rtree;
 pub mod s2;
 mod sec;
 pub mod stats;
+pub mod strategy;
 pub mod utils;
@@ -0,0 +1,216 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use super::Folder;
+use super::manifest::{self, Manifest};
+
+/// Directories that hold plugins of every folder, each in its own `routing`, `clustering` and `bootstrap` subdirectory
+const ROOTS_VAR: &str = "PLUGIN_DIRS";
+/// Directories below a root that are searched for plugins
+const MAX_DEPTH: usize = 4;
+/// Files with these extensions are plugins, as well as executables
+const EXTENSIONS: [&str; 4] = ["py", "js", "sh", "ts"];
+
+/// Last scan of every folder, reused until a file in one of its directories changes
+static SCANS: LazyLock<Mutex<HashMap<Folder, Scan>>> = LazyLock::new(Default::default);
+
+#[derive(Clone)]
+struct Scan {
+    fingerprint: u64,
+    plugins: Vec<Discovered>,
+}
+
+#[derive(Clone)]
+struct Discovered {
+    /// Path relative to its root, with `/` between directories
+    name: String,
+    manifest: Option<Manifest>,
+}
+
+/// Directories the plugins of the folder are loaded from, in order of precedence:
+/// `<FOLDER>_PLUGIN_DIRS`, then the folder in every `PLUGIN_DIRS` root, then `algorithms/src/<folder>/plugins`
+pub fn roots(folder: Folder) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = vec![];
+    if let Some(dirs) =
+        std::env::var_os(format!("{}_PLUGIN_DIRS", folder.to_string().to_uppercase()))
+    {
+        roots.extend(std::env::split_paths(&dirs));
+    }
+    if let Some(dirs) = std::env::var_os(ROOTS_VAR) {
+        roots.extend(std::env::split_paths(&dirs).map(|dir| dir.join(folder.to_string())));
+    }
+    roots.push(
+        Path::new("algorithms")
+            .join("src")
+            .join(folder.to_string())
+            .join("plugins"),
+    );
+
+    let mut unique: Vec<PathBuf> = vec![];
+    for root in roots {
+        let Ok(canonical) = root.canonicalize() else {
+            continue;
+        };
+        if canonical.is_dir() && !unique.contains(&canonical) {
+            unique.push(canonical);
+        }
+    }
+    unique
+}
+
+/// Path of the plugin, or of a file of a directory plugin, in the first root that has it.
+/// Names that leave the root are rejected
+pub fn locate(folder: Folder, name: &str) -> Option<PathBuf> {
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        log::warn!("ignoring plugin path outside of the plugin directories: {name}");
+        return None;
+    }
+    roots(folder)
+        .into_iter()
+        .map(|root| root.join(relative))
+        .find(|path| path.exists())
+}
+
+/// Names of every plugin of the folder, rescanned when its directories changed
+pub fn plugins(folder: Folder) -> Vec<String> {
+    scan(folder)
+        .plugins
+        .into_iter()
+        .map(|plugin| plugin.name)
+        .collect()
+}
+
+/// Manifests of every plugin of the folder that has one, by plugin name
+pub fn manifests(folder: Folder) -> BTreeMap<String, Manifest> {
+    scan(folder)
+        .plugins
+        .into_iter()
+        .filter_map(|plugin| Some((plugin.name, plugin.manifest?)))
+        .collect()
+}
+
+fn scan(folder: Folder) -> Scan {
+    let mut hasher = DefaultHasher::new();
+    let mut found: Vec<(String, PathBuf)> = vec![];
+    for root in roots(folder) {
+        root.hash(&mut hasher);
+        walk(&root, "", 0, &mut hasher, &mut found);
+    }
+    let fingerprint = hasher.finish();
+
+    let Ok(mut scans) = SCANS.lock() else {
+        return load(found, fingerprint);
+    };
+    match scans.get(&folder) {
+        Some(scan) if scan.fingerprint == fingerprint => scan.clone(),
+        _ => {
+            let scan = load(found, fingerprint);
+            log::info!("found {} {folder} plugins", scan.plugins.len());
+            scans.insert(folder, scan.clone());
+            scan
+        }
+    }
+}
+
+/// Plugins of the first root win over those with the same name in the others
+fn load(found: Vec<(String, PathBuf)>, fingerprint: u64) -> Scan {
+    let mut plugins: Vec<Discovered> = vec![];
+    for (name, path) in found {
+        if plugins.iter().any(|plugin| plugin.name == name) {
+            continue;
+        }
+        plugins.push(Discovered {
+            manifest: Manifest::load(&path.display().to_string()),
+            name,
+        });
+    }
+    Scan {
+        fingerprint,
+        plugins,
+    }
+}
+
+/// Lists the directory sorted by name and adds every entry with its size and modification time to the fingerprint.
+/// Directories with a manifest are plugins, the others are listed as before and searched for plugins of their own
+fn walk(
+    dir: &Path,
+    prefix: &str,
+    depth: usize,
+    hasher: &mut DefaultHasher,
+    found: &mut Vec<(String, PathBuf)>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    entries.sort();
+    for path in entries {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if file_name.starts_with('.') || file_name == "node_modules" || file_name == "__pycache__" {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        file_name.hash(hasher);
+        metadata.len().hash(hasher);
+        metadata.modified().ok().hash(hasher);
+
+        let name = format!("{prefix}{file_name}");
+        if metadata.is_dir() {
+            match fs::metadata(path.join(manifest::DIRECTORY_MANIFEST)) {
+                Ok(manifest) => {
+                    // editing the manifest doesn't touch the directory
+                    manifest.len().hash(hasher);
+                    manifest.modified().ok().hash(hasher);
+                    found.push((name, path));
+                }
+                Err(_) => {
+                    if depth == 0 {
+                        found.push((name.clone(), path.clone()));
+                    }
+                    if depth < MAX_DEPTH {
+                        walk(&path, &format!("{name}/"), depth + 1, hasher, found);
+                    }
+                }
+            }
+        } else if file_name.ends_with(manifest::SUFFIX) {
+            continue;
+        } else if depth == 0 || is_plugin(&path, &metadata) {
+            found.push((name, path));
+        }
+    }
+}
+
+/// Files in subdirectories are only listed when they look runnable, the others are usually part of a directory plugin
+fn is_plugin(path: &Path, metadata: &fs::Metadata) -> bool {
+    if path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension))
+    {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
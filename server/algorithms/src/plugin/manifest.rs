@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{Folder, discovery};
+
+/// File plugins have their manifest beside them as `<plugin>.manifest.json`
+pub const SUFFIX: &str = ".manifest.json";
+/// Directory plugins have their manifest inside the directory
+pub const DIRECTORY_MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+impl Display for ArgType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgType::String => write!(f, "string"),
+            ArgType::Number => write!(f, "number"),
+            ArgType::Integer => write!(f, "integer"),
+            ArgType::Boolean => write!(f, "boolean"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArgSchema {
+    #[serde(rename = "type")]
+    pub kind: ArgType,
+    /// Passed to the plugin when the arg is left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Values the arg is limited to, any value of its type when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<Value>,
+}
+
+impl ArgSchema {
+    /// Strings accept any value, since args that look like numbers are parsed as numbers
+    fn check(&self, key: &str, value: &mut Value) -> Result<(), String> {
+        let matches = match self.kind {
+            ArgType::String => {
+                if !value.is_string() {
+                    *value = Value::String(value.to_string());
+                }
+                true
+            }
+            ArgType::Number => value.is_number(),
+            ArgType::Integer => value.is_i64() || value.is_u64(),
+            ArgType::Boolean => value.is_boolean(),
+        };
+        if !matches {
+            return Err(format!(
+                "`--{key}` must be of type {}, received `{value}`",
+                self.kind
+            ));
+        }
+        if !self.options.is_empty() && !self.options.contains(value) {
+            return Err(format!(
+                "`--{key}` must be one of {}, received `{value}`",
+                Value::Array(self.options.clone())
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Optional description of a plugin, returned by `/config` so the client knows what args it accepts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    /// Replaces the interpreter picked by the file extension, args can still override it
+    pub interpreter: Option<String>,
+    /// Entry point of a directory plugin, relative to the directory
+    pub entry: Option<String>,
+    /// `1` for the text protocol and `2` for the JSON protocol, replaces the `koji-protocol` marker
+    pub protocol: Option<u8>,
+    /// In seconds, replaces the `koji-timeout` marker
+    pub timeout: Option<u64>,
+    /// Whether the plugin keeps running and answers one request line after the other,
+    /// replaces the `koji-worker` marker
+    pub worker: Option<bool>,
+    pub args: BTreeMap<String, ArgSchema>,
+    /// Data point categories the plugin supports, every category when empty
+    pub categories: Vec<String>,
+}
+
+impl Manifest {
+    /// `None` when the plugin has no manifest, or when it's invalid, which is logged
+    pub fn load(plugin_path: &str) -> Option<Self> {
+        let path = Path::new(plugin_path);
+        let manifest_path = if path.is_dir() {
+            path.join(DIRECTORY_MANIFEST)
+        } else {
+            Path::new(&format!("{plugin_path}{SUFFIX}")).to_path_buf()
+        };
+        let manifest = fs::read_to_string(&manifest_path).ok()?;
+        match serde_json::from_str(&manifest) {
+            Ok(manifest) => Some(manifest),
+            Err(err) => {
+                log::error!("invalid plugin manifest {}: {err}", manifest_path.display());
+                None
+            }
+        }
+    }
+
+    /// Manifests of every plugin in the folder that has one, by plugin name
+    pub fn all(folder: Folder) -> BTreeMap<String, Self> {
+        discovery::manifests(folder)
+    }
+
+    /// Checks the parsed args against the schema and adds the defaults of the missing ones,
+    /// returning the `--key value` args that were added
+    pub fn validate(&self, args: &mut Map<String, Value>) -> Result<Vec<String>, String> {
+        if let Some(unknown) = args.keys().find(|key| !self.args.contains_key(*key)) {
+            return Err(format!(
+                "unknown argument `--{unknown}`, expected one of: {}",
+                self.args
+                    .keys()
+                    .map(|key| format!("`--{key}`"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+        let mut added = vec![];
+        for (key, schema) in self.args.iter() {
+            match args.get_mut(key) {
+                Some(value) => schema.check(key, value)?,
+                None => match schema.default.as_ref() {
+                    Some(default) => {
+                        let mut default = default.clone();
+                        schema.check(key, &mut default)?;
+                        match &default {
+                            Value::Bool(false) => {}
+                            Value::Bool(true) => added.push(format!("--{key}")),
+                            Value::String(value) => {
+                                added.extend([format!("--{key}"), value.clone()])
+                            }
+                            value => added.extend([format!("--{key}"), value.to_string()]),
+                        }
+                        args.insert(key.clone(), default);
+                    }
+                    None if schema.required => {
+                        return Err(format!("missing required argument `--{key}`"));
+                    }
+                    None => {}
+                },
+            }
+        }
+        Ok(added)
+    }
+}
@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::progress::Progress;
+use crate::rtree::Weights;
+use crate::s2::{create_cell_map, from_array_to_cell_id};
+use crate::utils;
+use geojson::{Feature, FeatureCollection};
+use model::api::{Precision, point_array::PointArray, single_vec::SingleVec};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use self::manifest::Manifest;
+use self::process::{Pipe, SLOTS, Slot};
+use self::protocol::{
+    Context, PluginError, PluginReport, Protocol, Request, RequestPoint, Response,
+};
+use self::worker::{POOL, Worker};
+
+pub mod discovery;
+pub mod manifest;
+mod process;
+pub mod protocol;
+mod worker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Folder {
+    Routing,
+    Clustering,
+    Bootstrap,
+}
+
+impl Display for Folder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Folder::Routing => write!(f, "routing"),
+            Folder::Clustering => write!(f, "clustering"),
+            Folder::Bootstrap => write!(f, "bootstrap"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Plugin {
+    plugin_path: String,
+    interpreter: String,
+    args: Vec<String>,
+    folder: Folder,
+    context: Context,
+    weights: Weights,
+    report: Mutex<PluginReport>,
+    timeout: Duration,
+    progress: Progress,
+    /// Identifies the idle workers of the plugin that can be reused, `None` when the plugin runs once per request
+    worker_key: Option<String>,
+    pub protocol: Protocol,
+    pub plugin: String,
+    pub split_level: u64,
+}
+
+pub type JoinFunction = fn(&Plugin, Vec<SingleVec>) -> SingleVec;
+
+trait ParseCoord {
+    fn parse_next_coord(&mut self) -> Option<f64>;
+}
+
+impl ParseCoord for std::str::Split<'_, &str> {
+    fn parse_next_coord(&mut self) -> Option<f64> {
+        if let Some(coord) = self.next() {
+            if coord.contains(" ") {
+                return coord.split(",").parse_next_coord();
+            }
+            if let Ok(coord) = coord.parse::<f64>() {
+                return Some(coord);
+            }
+        }
+        None
+    }
+}
+
+impl Plugin {
+    pub fn new(
+        plugin: &str,
+        folder: Folder,
+        route_split_level: u64,
+        input_args: &str,
+    ) -> std::io::Result<Self> {
+        let Some(located) = discovery::locate(folder, plugin) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("plugin {plugin} does not exist"),
+            ));
+        };
+        let mut plugin_path = located.display().to_string();
+        let manifest = Manifest::load(&plugin_path);
+        let mut entry = plugin.to_string();
+        if let Some(manifest_entry) = manifest.as_ref().and_then(|m| m.entry.as_ref()) {
+            entry = format!("{plugin}/{manifest_entry}");
+            plugin_path = located.join(manifest_entry).display().to_string();
+        }
+        let mut interpreter = match manifest.as_ref().and_then(|m| m.interpreter.as_deref()) {
+            Some(interpreter) => interpreter,
+            None => match entry.split(".").last() {
+                Some("py") => "python3",
+                Some("js") => "node",
+                Some("sh") => "bash",
+                Some("ts") => "ts-node",
+                val => {
+                    if entry == val.unwrap_or("") {
+                        &plugin_path
+                    } else {
+                        ""
+                    }
+                }
+            },
+        }
+        .to_string();
+        let mut args = input_args
+            .split_whitespace()
+            .skip_while(|arg| !arg.starts_with("--"))
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>();
+
+        for (index, pre_arg) in input_args
+            .split_whitespace()
+            .take_while(|arg| !arg.starts_with("--"))
+            .enumerate()
+        {
+            log::info!("[PLUGIN PARSER] {index} | pre_arg: {}", pre_arg);
+            if index == 0 {
+                interpreter = pre_arg.to_string();
+            } else if index == 1 {
+                let Some(path) = discovery::locate(folder, pre_arg) else {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("{pre_arg} does not exist in the {folder} plugin directories"),
+                    ));
+                };
+                plugin_path = path.display().to_string();
+            } else {
+                log::warn!("Unrecognized argument: {pre_arg} for plugin: {plugin}")
+            }
+        }
+
+        if interpreter.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Unrecognized plugin, please create a PR to add support for it",
+            ));
+        };
+        let path = Path::new(&plugin_path);
+        if path.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "{plugin} is a directory, not a file, something may not be right with the provided args"
+                ),
+            ));
+        }
+        if path.exists() {
+            plugin_path = path.display().to_string();
+            if interpreter == plugin_path {
+                log::info!("{interpreter} {}", args.join(" "));
+            } else {
+                log::info!("{interpreter} {plugin_path} {}", args.join(" "));
+            }
+        } else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "{plugin} does not exist{}",
+                    if plugin == "tsp" {
+                        ", rerun the OR Tools Script"
+                    } else {
+                        ""
+                    }
+                ),
+            ));
+        }
+
+        let mut parsed_args = protocol::parse_args(&args);
+        let head = protocol::head(&plugin_path);
+        let mut protocol = Protocol::detect(&head);
+        let mut timeout = process::timeout(&head);
+        let mut is_worker = worker::declared(&head);
+        if let Some(manifest) = manifest {
+            let defaults = manifest.validate(&mut parsed_args).map_err(|err| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{plugin}: {err}"))
+            })?;
+            args.extend(defaults);
+            protocol = match manifest.protocol {
+                Some(1) => Protocol::Text,
+                Some(2) => Protocol::Json,
+                Some(version) => {
+                    log::warn!("{plugin} declares unknown protocol v{version}");
+                    protocol
+                }
+                None => protocol,
+            };
+            if let Some(seconds) = manifest.timeout {
+                timeout = Duration::from_secs(seconds);
+            }
+            is_worker = manifest.worker.unwrap_or(is_worker);
+        }
+        if protocol == Protocol::Json {
+            log::info!("{plugin} speaks protocol v{}", protocol.version());
+        }
+        // editing the plugin starts new workers
+        let worker_key = is_worker.then(|| {
+            let modified = fs::metadata(&plugin_path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            format!(
+                "{interpreter} {plugin_path} {} {modified:?}",
+                args.join(" ")
+            )
+        });
+
+        Ok(Plugin {
+            plugin: plugin.to_string(),
+            plugin_path,
+            interpreter,
+            split_level: route_split_level,
+            context: Context {
+                args: parsed_args,
+                ..Default::default()
+            },
+            args,
+            folder,
+            weights: Weights::default(),
+            report: Mutex::new(PluginReport {
+                plugin: plugin.to_string(),
+                ..Default::default()
+            }),
+            timeout,
+            progress: Progress::default(),
+            worker_key,
+            protocol,
+        })
+    }
+
+    /// The plugin is killed when the calculation is cancelled
+    pub fn set_progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Only sent to [Protocol::Json] plugins
+    pub fn set_radius(&mut self, radius: Precision) -> &mut Self {
+        self.context.radius = Some(radius);
+        self
+    }
+
+    /// Only sent to [Protocol::Json] plugins
+    pub fn set_area(&mut self, area: FeatureCollection) -> &mut Self {
+        self.context.area = Some(area);
+        self
+    }
+
+    /// Only sent to [Protocol::Json] plugins
+    pub fn set_weights(&mut self, weights: Weights) -> &mut Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Stats, point properties and errors the plugin returned.
+    /// `None` for [Protocol::Text] plugins that ran without errors
+    pub fn report(&self) -> Option<PluginReport> {
+        let report = self.report.lock().ok()?;
+        if self.protocol == Protocol::Text && report.errors.is_empty() {
+            return None;
+        }
+        Some(report.clone())
+    }
+
+    pub fn run_multi<T>(
+        &self,
+        points: &SingleVec,
+        joiner: Option<T>,
+    ) -> Result<SingleVec, std::io::Error>
+    where
+        T: Fn(&Self, Vec<SingleVec>) -> SingleVec,
+    {
+        let handlers = match self.protocol {
+            Protocol::Text if self.split_level == 0 => {
+                vec![self.run(utils::stringify_points(&points))?]
+            }
+            Protocol::Text => create_cell_map(&points, self.split_level)
+                .into_values()
+                .collect::<Vec<SingleVec>>()
+                .into_par_iter()
+                .filter_map(|x| self.run(utils::stringify_points(&x)).ok())
+                .collect(),
+            Protocol::Json if self.split_level == 0 => {
+                vec![self.run_json(points.iter().copied().enumerate().collect(), None)?]
+            }
+            Protocol::Json => {
+                let mut cell_map = HashMap::<u64, Vec<(usize, PointArray)>>::new();
+                for (id, point) in points.iter().enumerate() {
+                    cell_map
+                        .entry(from_array_to_cell_id(point, self.split_level).0)
+                        .or_default()
+                        .push((id, *point));
+                }
+                cell_map
+                    .into_values()
+                    .collect::<Vec<_>>()
+                    .into_par_iter()
+                    .filter_map(|cell| match self.run_json(cell, None) {
+                        Ok(points) => Some(points),
+                        Err(e) => {
+                            log::error!("Error while running plugin: {}", e);
+                            None
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        if let Some(joiner) = joiner {
+            Ok(joiner(self, handlers))
+        } else {
+            Ok(handlers.into_iter().flatten().collect())
+        }
+    }
+
+    pub fn run(&self, input: String) -> Result<SingleVec, std::io::Error> {
+        let time = Instant::now();
+        let output = self.execute(input)?;
+
+        let mut results = vec![];
+        let mut invalid = vec![];
+        for line in output.lines() {
+            if line.contains(" ") {
+                let mut iter: std::str::Split<'_, &str> = line.trim().split(" ");
+                while let Some(line) = iter.next() {
+                    let mut coord: std::str::Split<'_, &str> = line.trim().split(",");
+                    let lat = coord.parse_next_coord();
+                    let lng = coord.parse_next_coord();
+                    if lat.is_none() || lng.is_none() {
+                        invalid.push(line.to_string())
+                    } else {
+                        results.push([lat.unwrap(), lng.unwrap()])
+                    }
+                }
+            } else {
+                let mut iter: std::str::Split<'_, &str> = line.trim().split(",");
+                let lat = iter.parse_next_coord();
+                let lng = iter.parse_next_coord();
+                if lat.is_none() || lng.is_none() {
+                    invalid.push(line.to_string())
+                } else {
+                    results.push([lat.unwrap(), lng.unwrap()])
+                }
+            }
+        }
+
+        if let Some(first) = results.first() {
+            if let Some(last) = results.last() {
+                if first == last {
+                    results.pop();
+                }
+            }
+        }
+
+        if !invalid.is_empty() {
+            log::warn!(
+                "Some invalid results were returned from the plugin: `{}`",
+                invalid.join(", ")
+            );
+        }
+        if results.is_empty() {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "no valid output from child process \n{}\noutput should return points in the following format: `lat,lng lat,lng`",
+                    invalid.join(", ")
+                ),
+            ))
+        } else {
+            log::info!(
+                "{} child process finished in {}s with {} points",
+                self.plugin,
+                time.elapsed().as_secs_f32(),
+                results.len()
+            );
+            // Ok(output_indexes.into_iter().map(|i| points[i]).collect())
+            Ok(results)
+        }
+    }
+
+    /// Bootstrap plugins get the area instead of points, [Protocol::Json] plugins get it as the request's `area`
+    pub fn run_feature(&self, feature: &Feature) -> Result<SingleVec, std::io::Error> {
+        match self.protocol {
+            Protocol::Text => self.run(feature.to_string()),
+            Protocol::Json => self.run_json(
+                vec![],
+                Some(&FeatureCollection {
+                    bbox: None,
+                    features: vec![feature.clone()],
+                    foreign_members: None,
+                }),
+            ),
+        }
+    }
+
+    /// Sends the points with their ids to a [Protocol::Json] plugin, `area` replaces the area of the context
+    fn run_json(
+        &self,
+        points: Vec<(usize, PointArray)>,
+        area: Option<&FeatureCollection>,
+    ) -> Result<SingleVec, std::io::Error> {
+        let time = Instant::now();
+        let request = Request {
+            protocol: self.protocol.version(),
+            folder: self.folder.to_string(),
+            points: points
+                .iter()
+                .map(|(id, point)| RequestPoint {
+                    id: *id,
+                    lat: point[0],
+                    lng: point[1],
+                    weight: self.weights.get(&from_array_to_cell_id(point, 20)),
+                })
+                .collect(),
+            radius: self.context.radius,
+            area: area.or(self.context.area.as_ref()),
+            args: &self.context.args,
+        };
+        let input = serde_json::to_string(&request)?;
+        let output = self.execute(input)?;
+
+        let mut response: Response = serde_json::from_str(&output).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid response from {}: {e}\n{output}", self.plugin),
+            )
+        })?;
+        let mut results = response.resolve(&points.into_iter().collect());
+        if results.len() > 1 && results.first() == results.last() {
+            results.pop();
+        }
+        let errors = response
+            .errors
+            .iter()
+            .map(|error| format!("{}: {}", error.code, error.message))
+            .collect::<Vec<String>>()
+            .join(", ");
+        if !errors.is_empty() {
+            log::warn!("{} returned errors: {}", self.plugin, errors);
+        }
+        if let Ok(mut report) = self.report.lock() {
+            report.add(response, &results);
+        }
+        if results.is_empty() && !errors.is_empty() {
+            return Err(io::Error::other(errors));
+        }
+        log::info!(
+            "{} child process finished in {}s with {} points",
+            self.plugin,
+            time.elapsed().as_secs_f32(),
+            results.len()
+        );
+        Ok(results)
+    }
+
+    /// Runs the plugin with `input` on its stdin and returns its stdout.
+    /// Waits for a free process slot first and kills the plugin when it times out or the calculation is cancelled,
+    /// failures are added to the [PluginReport] together with the end of stderr
+    fn execute(&self, input: String) -> Result<String, std::io::Error> {
+        let result = self.execute_inner(input);
+        if let Err((code, message)) = result.as_ref()
+            && let Ok(mut report) = self.report.lock()
+        {
+            report.errors.push(PluginError {
+                code: code.to_string(),
+                message: message.clone(),
+            });
+        }
+        result.map_err(|(_, message)| io::Error::other(message))
+    }
+
+    fn execute_inner(&self, input: String) -> Result<String, (&'static str, String)> {
+        match self.worker_key.as_ref() {
+            Some(key) => self.execute_worker(input, key),
+            None => {
+                let _slot = self.acquire_slot()?;
+                self.execute_once(input)
+            }
+        }
+    }
+
+    /// A slot of the process cap, idle workers are stopped to free one
+    fn acquire_slot(&self) -> Result<Slot, (&'static str, String)> {
+        SLOTS
+            .acquire(&self.progress, || POOL.evict_oldest())
+            .ok_or_else(|| ("cancelled", format!("{} was cancelled", self.plugin)))
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.interpreter);
+        if self.plugin_path != self.interpreter {
+            command.arg(&self.plugin_path);
+        };
+        process::isolate(&mut command)
+            .args(self.args.iter())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        command
+    }
+
+    /// Error when the calculation was cancelled or the deadline passed
+    fn interrupted(&self, deadline: Instant) -> Option<(&'static str, String)> {
+        if self.progress.is_cancelled() {
+            Some(("cancelled", format!("{} was cancelled", self.plugin)))
+        } else if Instant::now() >= deadline {
+            Some((
+                "timeout",
+                format!(
+                    "{} timed out after {}s",
+                    self.plugin,
+                    self.timeout.as_secs_f32()
+                ),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Logs stderr and appends its end to the message of an error
+    fn with_stderr<T>(
+        &self,
+        result: Result<T, (&'static str, String)>,
+        stderr: &str,
+    ) -> Result<T, (&'static str, String)> {
+        for line in stderr.lines().filter(|line| !line.trim().is_empty()) {
+            log::warn!("[{}] {}", self.plugin, line);
+        }
+        result.map_err(|(code, message)| {
+            if stderr.trim().is_empty() {
+                (code, message)
+            } else {
+                (code, format!("{message}\n{}", process::tail(stderr)))
+            }
+        })
+    }
+
+    fn execute_once(&self, input: String) -> Result<String, (&'static str, String)> {
+        log::info!("spawning {} child process", self.plugin);
+        let mut child = self
+            .command()
+            .spawn()
+            .map_err(|err| ("spawn", format!("unable to start {}: {err}", self.plugin)))?;
+
+        let (Some(mut stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            process::kill(&mut child);
+            return Err((
+                "spawn",
+                "Failed to open stdin, stdout or stderr".to_string(),
+            ));
+        };
+
+        std::thread::spawn(move || match stdin.write_all(input.as_bytes()) {
+            Ok(_) => match stdin.flush() {
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!("failed to flush stdin: {}", err);
+                }
+            },
+            Err(err) => {
+                log::error!("failed to write to stdin: {}", err)
+            }
+        });
+        let stdout = Pipe::read(stdout);
+        let stderr = Pipe::read(stderr);
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => match self.interrupted(deadline) {
+                    Some(err) => break Err(err),
+                    None => std::thread::sleep(process::POLL_INTERVAL),
+                },
+                Err(err) => break Err(("wait", err.to_string())),
+            }
+        };
+        if status.is_err() {
+            process::kill(&mut child);
+        }
+        let stdout = stdout.finish(status.is_ok());
+        let stderr = stderr.finish(status.is_ok());
+
+        let result = match status {
+            Ok(status) if status.success() => Ok(stdout),
+            Ok(status) => Err((
+                "exit_status",
+                format!("child process exited with status: {}", status),
+            )),
+            Err(err) => Err(err),
+        };
+        self.with_stderr(result, &stderr)
+    }
+
+    /// Sends the input as one line to an idle worker of the plugin, or a new one.
+    /// Workers that failed are stopped, the others go back to the pool
+    fn execute_worker(&self, input: String, key: &str) -> Result<String, (&'static str, String)> {
+        let mut worker = match POOL.checkout(key) {
+            Some(worker) => worker,
+            None => {
+                let slot = self.acquire_slot()?;
+                log::info!("spawning {} worker process", self.plugin);
+                Worker::spawn(&mut self.command(), slot)
+                    .map_err(|err| ("spawn", format!("unable to start {}: {err}", self.plugin)))?
+            }
+        };
+        let deadline = Instant::now() + self.timeout;
+        let result = worker.request(input, || self.interrupted(deadline));
+        let stderr = worker.stderr.take();
+        if result.is_ok() {
+            POOL.checkin(key, worker);
+        }
+        self.with_stderr(result, &stderr)
+    }
+}
@@ -0,0 +1,179 @@
+use std::io::Read;
+use std::process::{Child, Command};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::progress::Progress;
+
+/// Seconds a plugin may run before it's killed, `PLUGIN_TIMEOUT` overrides it
+const DEFAULT_TIMEOUT: u64 = 600;
+/// A plugin sets its own timeout in seconds with this marker in its [super::protocol::head]
+const TIMEOUT_MARKER: &str = "koji-timeout:";
+/// How often a running plugin is checked for its timeout and for cancellation
+pub const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Characters of stderr that are kept for error messages
+const STDERR_TAIL: usize = 2_000;
+
+/// Child processes every plugin of every request shares, `PLUGIN_MAX_PROCESSES` overrides the number of CPUs
+pub static SLOTS: LazyLock<Slots> = LazyLock::new(|| {
+    let max = std::env::var("PLUGIN_MAX_PROCESSES")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|cpus| cpus.get())
+                .unwrap_or(4)
+        });
+    log::info!("running up to {max} plugin processes at once");
+    Slots {
+        max,
+        used: Mutex::new(0),
+        freed: Condvar::new(),
+    }
+});
+
+/// The timeout the plugin declared, otherwise `PLUGIN_TIMEOUT` or [DEFAULT_TIMEOUT]
+pub fn timeout(head: &str) -> Duration {
+    let declared = head.lines().find_map(|line| {
+        let (_, seconds) = line.split_once(TIMEOUT_MARKER)?;
+        seconds.split_whitespace().next()?.parse::<u64>().ok()
+    });
+    Duration::from_secs(declared.unwrap_or_else(|| {
+        std::env::var("PLUGIN_TIMEOUT")
+            .ok()
+            .and_then(|timeout| timeout.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }))
+}
+
+/// The end of the output, where the actual error usually is
+pub fn tail(output: &str) -> &str {
+    let output = output.trim_end();
+    match output.char_indices().rev().nth(STDERR_TAIL - 1) {
+        Some((index, _)) => &output[index..],
+        None => output,
+    }
+}
+
+/// Starts the plugin in its own process group, so [kill] also reaches the processes it started
+pub fn isolate(command: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command
+}
+
+pub fn kill(child: &mut Child) {
+    // once the child was reaped its pid may belong to another process
+    if !matches!(child.try_wait(), Ok(None)) {
+        return;
+    }
+    #[cfg(unix)]
+    // SAFETY: only sends a signal, the group was created for the child by [isolate]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    if let Err(err) = child.kill() {
+        log::debug!("failed to kill child process: {}", err);
+    }
+    let _ = child.wait();
+}
+
+/// Reads a pipe of the plugin on its own thread, so a plugin that hangs can't block the deadline checks
+pub struct Pipe {
+    output: Arc<Mutex<Vec<u8>>>,
+    reader: JoinHandle<()>,
+}
+
+impl Pipe {
+    pub fn read(mut pipe: impl Read + Send + 'static) -> Self {
+        let output = Arc::new(Mutex::new(vec![]));
+        let shared = output.clone();
+        let reader = std::thread::spawn(move || {
+            let mut buffer = [0; 8192];
+            loop {
+                match pipe.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(read) => {
+                        if let Ok(mut output) = shared.lock() {
+                            output.extend_from_slice(&buffer[..read]);
+                        }
+                    }
+                    Err(err) => {
+                        log::error!("failed to read from child process: {}", err);
+                        break;
+                    }
+                }
+            }
+        });
+        Self { output, reader }
+    }
+
+    /// What was read so far, for pipes that stay open between requests
+    pub fn take(&self) -> String {
+        self.output
+            .lock()
+            .map(|mut output| String::from_utf8_lossy(&std::mem::take(&mut *output)).into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Waits for the pipe to close, unless the plugin was killed and something it started may still hold it open
+    pub fn finish(self, wait: bool) -> String {
+        if wait {
+            let _ = self.reader.join();
+        }
+        self.output
+            .lock()
+            .map(|output| String::from_utf8_lossy(&output).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub struct Slots {
+    max: usize,
+    used: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Slots {
+    /// Blocks until a process may be started, `None` when the calculation was cancelled while waiting.
+    /// While every slot is taken, `evict` is asked to free one, it returns whether it did
+    pub fn acquire(&'static self, progress: &Progress, evict: impl Fn() -> bool) -> Option<Slot> {
+        loop {
+            let mut used = self.used.lock().ok()?;
+            if *used < self.max {
+                *used += 1;
+                return Some(Slot(self));
+            }
+            if progress.is_cancelled() {
+                return None;
+            }
+            // the slot of an evicted worker is freed when it's dropped, which needs the lock
+            drop(used);
+            if evict() {
+                continue;
+            }
+            let used = self.used.lock().ok()?;
+            if *used >= self.max {
+                drop(self.freed.wait_timeout(used, POLL_INTERVAL).ok()?);
+            }
+        }
+    }
+}
+
+/// Frees its slot when dropped
+pub struct Slot(&'static Slots);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Ok(mut used) = self.0.used.lock() {
+            *used -= 1;
+        }
+        self.0.freed.notify_one();
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use geojson::FeatureCollection;
+use model::api::{Precision, point_array::PointArray, single_vec::SingleVec};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A plugin opts into [Protocol::Json] by having this marker in its [head], usually as a comment
+const MARKER: &str = "koji-protocol: 2";
+/// Bytes at the start of a plugin that are searched for its markers
+const HEAD_BYTES: usize = 1024;
+
+/// The start of the plugin file, where it can declare `koji-` markers
+pub fn head(plugin_path: &str) -> String {
+    let mut head = vec![];
+    match File::open(plugin_path) {
+        Ok(file) => {
+            if let Err(err) = file.take(HEAD_BYTES as u64).read_to_end(&mut head) {
+                log::warn!("unable to read {plugin_path}: {err}");
+            }
+        }
+        Err(err) => log::warn!("unable to open {plugin_path}: {err}"),
+    }
+    String::from_utf8_lossy(&head).into_owned()
+}
+
+/// How a plugin talks to Kōji over stdin and stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// `lat,lng` pairs separated by spaces in, lines of coordinates out
+    #[default]
+    Text,
+    /// One [Request] document in, one [Response] document out
+    Json,
+}
+
+impl Protocol {
+    pub fn detect(head: &str) -> Self {
+        if head.contains(MARKER) {
+            Protocol::Json
+        } else {
+            Protocol::Text
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        match self {
+            Protocol::Text => 1,
+            Protocol::Json => 2,
+        }
+    }
+}
+
+/// Context the plugin runs with besides the points, set once per [super::Plugin]
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub radius: Option<Precision>,
+    pub area: Option<FeatureCollection>,
+    pub args: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestPoint {
+    /// Index of the point in the input of the whole run, also when the points were split by `split_level`
+    pub id: usize,
+    pub lat: Precision,
+    pub lng: Precision,
+    /// Normalized so the average point weighs `1.0`
+    pub weight: Precision,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Request<'a> {
+    pub protocol: u8,
+    pub folder: String,
+    pub points: Vec<RequestPoint>,
+    pub radius: Option<Precision>,
+    pub area: Option<&'a FeatureCollection>,
+    pub args: &'a Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginError {
+    pub code: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Response {
+    /// Ids of the request points in the order the plugin returns them, takes precedence over `points`
+    pub order: Option<Vec<usize>>,
+    /// New `[lat, lng]` coordinates, for plugins that don't only reorder or pick request points
+    pub points: SingleVec,
+    /// Extra properties of the returned points, by their position in the result
+    pub properties: Vec<Map<String, Value>>,
+    pub stats: Map<String, Value>,
+    pub errors: Vec<PluginError>,
+}
+
+impl Response {
+    /// Resolves the returned points, ids that aren't in the request are reported as errors
+    /// and dropped together with their properties, so the properties stay paired with the result
+    pub fn resolve(&mut self, request: &HashMap<usize, PointArray>) -> SingleVec {
+        let Some(order) = self.order.take() else {
+            return std::mem::take(&mut self.points);
+        };
+        let mut properties = std::mem::take(&mut self.properties).into_iter();
+        let mut resolved = vec![];
+        for id in order {
+            let point_properties = properties.next();
+            match request.get(&id) {
+                Some(point) => {
+                    resolved.push(*point);
+                    self.properties.extend(point_properties);
+                }
+                None => self.errors.push(PluginError {
+                    code: "unknown_id".to_string(),
+                    message: format!("point {id} was not part of the request"),
+                }),
+            }
+        }
+        resolved
+    }
+}
+
+/// Properties the plugin returned for one of its result points
+#[derive(Debug, Clone, Serialize)]
+pub struct PointProperties {
+    pub point: PointArray,
+    pub properties: Map<String, Value>,
+}
+
+/// Everything besides the points that [Protocol::Json] plugins returned during a run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PluginReport {
+    pub plugin: String,
+    /// Custom stats, numbers are added up over the runs of every `split_level` cell, other values are replaced
+    pub stats: Map<String, Value>,
+    pub properties: Vec<PointProperties>,
+    pub errors: Vec<PluginError>,
+}
+
+impl PluginReport {
+    pub fn add(&mut self, response: Response, points: &SingleVec) {
+        self.add_stats(response.stats);
+        self.properties.extend(
+            response
+                .properties
+                .into_iter()
+                .zip(points.iter())
+                .filter(|(properties, _)| !properties.is_empty())
+                .map(|(properties, point)| PointProperties {
+                    point: *point,
+                    properties,
+                }),
+        );
+        self.errors.extend(response.errors);
+    }
+
+    pub fn merge(&mut self, rhs: &Self) {
+        if self.plugin.is_empty() {
+            self.plugin = rhs.plugin.clone();
+        }
+        self.add_stats(rhs.stats.clone());
+        self.properties.extend(rhs.properties.iter().cloned());
+        self.errors.extend(rhs.errors.iter().cloned());
+    }
+
+    fn add_stats(&mut self, stats: Map<String, Value>) {
+        for (key, value) in stats {
+            let current = self.stats.get(&key);
+            let sum = match (current.and_then(Value::as_i64), value.as_i64()) {
+                (Some(current), Some(new)) => Some(serde_json::Number::from(current + new)),
+                _ => match (current.and_then(Value::as_f64), value.as_f64()) {
+                    (Some(current), Some(new)) => serde_json::Number::from_f64(current + new),
+                    _ => None,
+                },
+            };
+            self.stats.insert(key, sum.map_or(value, Value::Number));
+        }
+    }
+}
+
+/// `--key value` pairs become `"key": value`, values that parse as JSON keep their type,
+/// flags without a value become `true`
+pub fn parse_args(args: &[String]) -> Map<String, Value> {
+    let mut parsed = Map::new();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        let Some(key) = arg.strip_prefix("--") else {
+            log::warn!("ignoring plugin arg without a key: {arg}");
+            continue;
+        };
+        let (key, value) = match key.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (key, iter.next_if(|next| !next.starts_with("--")).cloned()),
+        };
+        let value = match value {
+            Some(value) => serde_json::from_str(&value).unwrap_or(Value::String(value)),
+            None => Value::Bool(true),
+        };
+        parsed.insert(key.to_string(), value);
+    }
+    parsed
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, Command};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use super::process::{self, Pipe, Slot};
+
+/// A plugin declares itself long-lived with this marker in its [super::protocol::head]
+const MARKER: &str = "koji-worker: true";
+/// Idle workers kept per plugin, the others are stopped once their request is done
+const MAX_IDLE: usize = 4;
+/// Idle workers are stopped after this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Idle workers of every plugin, shared by all requests. Idle workers keep their [Slot],
+/// the oldest one is stopped when a process needs a slot and there is none left
+pub static POOL: LazyLock<Pool> = LazyLock::new(Pool::default);
+
+pub fn declared(head: &str) -> bool {
+    head.contains(MARKER)
+}
+
+/// Idle workers by [super::Plugin] command, a worker is only reused with the exact same args
+#[derive(Default)]
+pub struct Pool(Mutex<HashMap<String, Vec<Worker>>>);
+
+impl Pool {
+    /// The most recently used worker that is still alive, the others are stopped on the way
+    pub fn checkout(&self, key: &str) -> Option<Worker> {
+        let mut idle = self.0.lock().ok()?;
+        reap(&mut idle);
+        let workers = idle.get_mut(key)?;
+        while let Some(mut worker) = workers.pop() {
+            if worker.is_alive() {
+                return Some(worker);
+            }
+        }
+        None
+    }
+
+    pub fn checkin(&self, key: &str, worker: Worker) {
+        let Ok(mut idle) = self.0.lock() else {
+            return;
+        };
+        reap(&mut idle);
+        let workers = idle.entry(key.to_string()).or_default();
+        if workers.len() < MAX_IDLE {
+            workers.push(worker);
+        }
+    }
+
+    /// Stops the least recently used idle worker, freeing its slot. `false` if there was none
+    pub fn evict_oldest(&self) -> bool {
+        let worker = {
+            let Ok(mut idle) = self.0.lock() else {
+                return false;
+            };
+            let Some((key, index)) = idle
+                .iter()
+                .flat_map(|(key, workers)| {
+                    workers
+                        .iter()
+                        .enumerate()
+                        .map(move |(index, worker)| (key, index, worker.last_used))
+                })
+                .min_by_key(|(.., last_used)| *last_used)
+                .map(|(key, index, _)| (key.clone(), index))
+            else {
+                return false;
+            };
+            let worker = idle.get_mut(&key).map(|workers| workers.remove(index));
+            idle.retain(|_, workers| !workers.is_empty());
+            worker
+        };
+        worker.is_some()
+    }
+}
+
+/// Stops the workers that were idle for too long and forgets the commands without workers
+fn reap(idle: &mut HashMap<String, Vec<Worker>>) {
+    for workers in idle.values_mut() {
+        workers.retain(|worker| worker.last_used.elapsed() < IDLE_TIMEOUT);
+    }
+    idle.retain(|_, workers| !workers.is_empty());
+}
+
+/// A plugin process that answers one line on stdin with one line on stdout, until its stdin is closed.
+/// It's killed when dropped, which frees its slot
+pub struct Worker {
+    child: Child,
+    _slot: Slot,
+    requests: Sender<String>,
+    responses: Receiver<String>,
+    pub stderr: Pipe,
+    last_used: Instant,
+}
+
+impl Worker {
+    pub fn spawn(command: &mut Command, slot: Slot) -> Result<Self, io::Error> {
+        let mut child = command.spawn()?;
+        let (Some(mut stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            process::kill(&mut child);
+            return Err(io::Error::other("Failed to open stdin, stdout or stderr"));
+        };
+
+        let (requests, inbox) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for request in inbox {
+                if let Err(err) = stdin
+                    .write_all(request.as_bytes())
+                    .and_then(|_| stdin.write_all(b"\n"))
+                    .and_then(|_| stdin.flush())
+                {
+                    log::error!("failed to write to stdin: {}", err);
+                    break;
+                }
+            }
+        });
+        let (outbox, responses) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if outbox.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        log::error!("failed to read from child process: {}", err);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            _slot: slot,
+            requests,
+            responses,
+            stderr: Pipe::read(stderr),
+            last_used: Instant::now(),
+        })
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Sends `input` as one line and waits for the line that answers it.
+    /// `interrupted` is checked while waiting, the worker must not be reused after it returned an error
+    pub fn request(
+        &mut self,
+        input: String,
+        interrupted: impl Fn() -> Option<(&'static str, String)>,
+    ) -> Result<String, (&'static str, String)> {
+        // lines without a request would answer this one
+        while self.responses.try_recv().is_ok() {}
+        if self.requests.send(input).is_err() {
+            return Err(("exit_status", "worker stopped reading requests".to_string()));
+        }
+        loop {
+            match self.responses.recv_timeout(process::POLL_INTERVAL) {
+                Ok(line) => {
+                    self.last_used = Instant::now();
+                    return Ok(line);
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(err) = interrupted() {
+                        return Err(err);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err((
+                        "exit_status",
+                        match self.child.wait() {
+                            Ok(status) => format!("worker exited with status: {}", status),
+                            Err(err) => format!("worker closed stdout: {}", err),
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        process::kill(&mut self.child);
+    }
+}
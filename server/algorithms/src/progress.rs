@@ -0,0 +1,76 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use model::api::Precision;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub phase: String,
+    /// 0 to 100, restarts with every phase
+    pub percent: f32,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    phase: Mutex<String>,
+    percent: AtomicU32,
+    cancelled: AtomicBool,
+}
+
+/// Handle that long running calculations report their progress to and check for cancellation.
+/// Clones share the same state, so one can be kept by the caller while the other is passed down
+#[derive(Debug, Clone, Default)]
+pub struct Progress(Arc<State>);
+
+impl Progress {
+    pub fn set_phase(&self, phase: &str) {
+        if let Ok(mut current) = self.0.phase.lock() {
+            *current = phase.to_string();
+        }
+        self.0.percent.store(0, Ordering::Relaxed);
+        log::debug!("Phase: {}", phase);
+    }
+
+    pub fn set_percent(&self, percent: f32) {
+        self.0
+            .percent
+            .store(percent.clamp(0., 100.).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Sets the percentage from how many of the total steps are done
+    pub fn set_steps(&self, done: usize, total: usize) {
+        if total > 0 {
+            self.set_percent((done as Precision / total as Precision * 100.) as f32);
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// True when both handles belong to the same calculation
+    pub fn same_as(&self, other: &Progress) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            phase: self
+                .0
+                .phase
+                .lock()
+                .map(|phase| phase.clone())
+                .unwrap_or_default(),
+            percent: f32::from_bits(self.0.percent.load(Ordering::Relaxed)),
+            cancelled: self.is_cancelled(),
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::time::{Duration, Instant};
+
+use geo::{Distance, Haversine, Point};
+use model::api::{Precision, args::RouteStart, single_vec::SingleVec};
+use rstar::{RTree, primitives::GeomWithData};
+
+use super::tsp;
+use crate::{
+    progress::Progress,
+    stats::{CycleModel, Stats},
+    utils,
+};
+
+/// Rotations of the full tour that are tried as the start of the first sub-route
+const START_OFFSETS: usize = 64;
+/// Bisection steps when searching for the shortest cycle the tour can be cut by
+const BISECTIONS: usize = 40;
+/// How many of the closest clusters decide which sub-routes a cluster can be moved to
+const NEIGHBORS: usize = 10;
+/// Minimum gain (in meters) for a move to be applied
+const EPSILON: Precision = 1e-7;
+/// Wall-clock time balancing the sub-routes may take, the moves found until then are kept
+const BALANCE_TIME_BUDGET: Duration = Duration::from_secs(10);
+
+/// Meters between the points, or seconds when balancing the cycle time
+fn dist(a: &[Precision; 2], b: &[Precision; 2], cycle_model: Option<&CycleModel>) -> Precision {
+    let meters = Haversine.distance(Point::new(a[1], a[0]), Point::new(b[1], b[0]));
+    match cycle_model {
+        Some(cycle_model) => cycle_model.hop_time(meters),
+        None => meters,
+    }
+}
+
+/// Length of the closed loop through the points, in order
+fn cycle(points: &SingleVec, route: &[usize], cycle_model: Option<&CycleModel>) -> Precision {
+    route
+        .iter()
+        .zip(route.iter().cycle().skip(1))
+        .map(|(a, b)| dist(&points[*a], &points[*b], cycle_model))
+        .sum()
+}
+
+/// Cuts the tour into consecutive `[start, end)` ranges whose closed loops don't exceed `limit`
+fn cut(
+    tour: &SingleVec,
+    prefix: &[Precision],
+    limit: Precision,
+    cycle_model: Option<&CycleModel>,
+) -> Vec<(usize, usize)> {
+    let mut segments = vec![];
+    let mut start = 0;
+    for end in 1..tour.len() {
+        if prefix[end] - prefix[start] + dist(&tour[end], &tour[start], cycle_model) > limit {
+            segments.push((start, end));
+            start = end;
+        }
+    }
+    segments.push((start, tour.len()));
+    segments
+}
+
+/// Route first, cluster second: tries several starting points along the full tour and cuts
+/// it into `devices` consecutive pieces, keeping the longest closed loop as short as possible.
+/// Without `rotate` the first piece starts at the start of the tour
+fn split_tour(
+    tour: &SingleVec,
+    devices: usize,
+    cycle_model: Option<&CycleModel>,
+    rotate: bool,
+) -> Vec<Vec<usize>> {
+    let n = tour.len();
+    let offsets = if rotate { START_OFFSETS.min(n) } else { 1 };
+    let mut best_longest = Precision::MAX;
+    let mut best_offset = 0;
+    let mut segments = vec![];
+
+    for offset in (0..offsets).map(|i| i * n / offsets) {
+        let mut rotated = tour.clone();
+        rotated.rotate_left(offset);
+
+        let mut prefix = vec![0.; n];
+        for i in 1..n {
+            prefix[i] = prefix[i - 1] + dist(&rotated[i - 1], &rotated[i], cycle_model);
+        }
+        let mut low = 0.;
+        let mut high = prefix[n - 1] + dist(&rotated[n - 1], &rotated[0], cycle_model);
+        for _ in 0..BISECTIONS {
+            let mid = (low + high) / 2.;
+            if cut(&rotated, &prefix, mid, cycle_model).len() <= devices {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        let candidate = cut(&rotated, &prefix, high, cycle_model);
+        let longest = candidate
+            .iter()
+            .map(|(start, end)| {
+                prefix[end - 1] - prefix[*start]
+                    + dist(&rotated[end - 1], &rotated[*start], cycle_model)
+            })
+            .fold(0., Precision::max);
+        if longest < best_longest {
+            best_longest = longest;
+            best_offset = offset;
+            segments = candidate;
+        }
+    }
+
+    // a tour that is easy to cut may need fewer pieces than there are devices
+    while segments.len() < devices {
+        let Some(index) = (0..segments.len())
+            .filter(|i| segments[*i].1 - segments[*i].0 > 1)
+            .max_by_key(|i| segments[*i].1 - segments[*i].0)
+        else {
+            break;
+        };
+        let (start, end) = segments[index];
+        let mid = start + (end - start) / 2;
+        segments[index] = (start, mid);
+        segments.insert(index + 1, (mid, end));
+    }
+    segments
+        .into_iter()
+        .map(|(start, end)| (start..end).map(|i| (i + best_offset) % n).collect())
+        .collect()
+}
+
+fn neighbor_lists(points: &SingleVec) -> Vec<Vec<usize>> {
+    let lat_scale = points.iter().map(|p| p[0]).sum::<Precision>() / points.len() as Precision;
+    let lat_scale = lat_scale.to_radians().cos();
+    let project = |p: &[Precision; 2]| [p[1] * lat_scale, p[0]];
+
+    let tree = RTree::bulk_load(
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| GeomWithData::new(project(p), i))
+            .collect(),
+    );
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            tree.nearest_neighbor_iter(&project(p))
+                .filter(|n| n.data != i)
+                .take(NEIGHBORS)
+                .map(|n| n.data)
+                .collect()
+        })
+        .collect()
+}
+
+/// Cheapest position and added length for inserting `point` into the closed `route`, only trying the
+/// positions next to its nearest neighbors that the route owns
+fn cheapest_insertion(
+    points: &SingleVec,
+    route: &[usize],
+    positions: &[usize],
+    neighbors: &[usize],
+    owned: impl Fn(usize) -> bool,
+    point: usize,
+    cycle_model: Option<&CycleModel>,
+) -> Option<(usize, Precision)> {
+    let added = |i: usize| {
+        let a = &points[route[i]];
+        let b = &points[route[(i + 1) % route.len()]];
+        dist(a, &points[point], cycle_model) + dist(&points[point], b, cycle_model)
+            - dist(a, b, cycle_model)
+    };
+    neighbors
+        .iter()
+        .filter(|neighbor| owned(**neighbor))
+        .flat_map(|neighbor| {
+            let position = positions[*neighbor];
+            [(position + route.len() - 1) % route.len(), position]
+        })
+        .map(|i| (i + 1, added(i)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Moves clusters off the longest sub-route onto a neighboring one for as long as that shortens
+/// the longest sub-route. Only sub-routes that own one of the cluster's nearest neighbors are
+/// considered so the sub-routes stay apart.
+/// Stops after [BALANCE_TIME_BUDGET], keeping the moves that were made
+fn balance(
+    points: &SingleVec,
+    routes: &mut [Vec<usize>],
+    cycle_model: Option<&CycleModel>,
+    progress: &Progress,
+) {
+    let neighbors = neighbor_lists(points);
+    let mut owner = vec![0; points.len()];
+    let mut positions = vec![0; points.len()];
+    for (index, route) in routes.iter().enumerate() {
+        for (position, point) in route.iter().enumerate() {
+            owner[*point] = index;
+            positions[*point] = position;
+        }
+    }
+    let time = Instant::now();
+    let mut lengths: Vec<Precision> = routes
+        .iter()
+        .map(|route| cycle(points, route, cycle_model))
+        .collect();
+
+    for _ in 0..points.len() {
+        if progress.is_cancelled() || time.elapsed() > BALANCE_TIME_BUDGET {
+            break;
+        }
+        let Some(longest) = (0..routes.len()).max_by(|a, b| lengths[*a].total_cmp(&lengths[*b]))
+        else {
+            break;
+        };
+        let route = &routes[longest];
+        if route.len() < 2 {
+            break;
+        }
+
+        // (position in the longest route, target route, insert position, new lengths)
+        let mut best: Option<(usize, usize, usize, Precision, Precision)> = None;
+        for (position, point) in route.iter().enumerate() {
+            let prev = &points[route[(position + route.len() - 1) % route.len()]];
+            let next = &points[route[(position + 1) % route.len()]];
+            let removed = lengths[longest]
+                - dist(prev, &points[*point], cycle_model)
+                - dist(&points[*point], next, cycle_model)
+                + dist(prev, next, cycle_model);
+
+            let mut targets: Vec<usize> = neighbors[*point]
+                .iter()
+                .map(|neighbor| owner[*neighbor])
+                .filter(|target| *target != longest)
+                .collect();
+            targets.sort_unstable();
+            targets.dedup();
+
+            for target in targets {
+                let Some((insert_at, added)) = cheapest_insertion(
+                    points,
+                    &routes[target],
+                    &positions,
+                    &neighbors[*point],
+                    |neighbor| owner[neighbor] == target,
+                    *point,
+                    cycle_model,
+                ) else {
+                    continue;
+                };
+                let inserted = lengths[target] + added;
+                let new_longest = removed.max(inserted);
+                if new_longest < lengths[longest] - EPSILON
+                    && best
+                        .as_ref()
+                        .is_none_or(|(.., a, b)| new_longest < a.max(*b))
+                {
+                    best = Some((position, target, insert_at, removed, inserted));
+                }
+            }
+        }
+
+        let Some((position, target, insert_at, removed, inserted)) = best else {
+            break;
+        };
+        let point = routes[longest].remove(position);
+        routes[target].insert(insert_at, point);
+        owner[point] = target;
+        lengths[longest] = removed;
+        lengths[target] = inserted;
+        for index in [longest, target] {
+            for (position, point) in routes[index].iter().enumerate() {
+                positions[*point] = position;
+            }
+        }
+    }
+}
+
+/// Splits the clusters into one closed, TSP ordered sub-route per device with roughly equal
+/// lengths. The full set is routed with the native TSP solver first, so `sort_by` does not apply.
+/// Lengths are cycle times instead of distances when the cycle model of the stats asks for it.
+/// With a start, every sub-route begins at its cluster closest to it.
+/// Open routes are split the same way, then every sub-route is solved as a path between the start and end.
+/// With `preserve_order` the clusters are only cut into consecutive pieces in the order they were sent
+pub fn main(
+    clusters: &SingleVec,
+    devices: usize,
+    stats: &mut Stats,
+    route_start: &RouteStart,
+    progress: &Progress,
+) -> Vec<SingleVec> {
+    if progress.is_cancelled() {
+        log::warn!("device routing was cancelled");
+        return vec![clusters.clone()];
+    }
+    let route_time = Instant::now();
+    let devices = devices.clamp(1, clusters.len().max(1));
+    let start = route_start.point(clusters);
+    let end = route_start.end_point(clusters);
+    stats.set_open_route(route_start.open);
+
+    let cycle_model = stats.cycle_model().clone();
+    let cycle_model = cycle_model.minimize.then_some(&cycle_model);
+    let max_hop = stats.hop_limit().map(|hop_limit| hop_limit.max_distance);
+    let solve = |route: &SingleVec, progress: &Progress| {
+        if route_start.preserve_order {
+            match start {
+                Some(start) if !route_start.open => utils::rotate_to_point(route.clone(), start),
+                _ => route.clone(),
+            }
+        } else if route_start.open {
+            tsp::solve_path(
+                route,
+                start.map(|start| utils::closest(route, start)),
+                end.map(|end| utils::closest(route, end)),
+                cycle_model,
+                max_hop,
+                progress,
+            )
+            .route
+        } else {
+            let route = tsp::solve_with(route, cycle_model, max_hop, progress).route;
+            match start {
+                Some(start) => utils::rotate_to_point(route, start),
+                None => route,
+            }
+        }
+    };
+
+    progress.set_phase("routing");
+    if devices == 1 {
+        let tour = stats.fill_long_hops(solve(clusters, progress));
+        stats.set_route_time(route_time);
+        stats.distance_stats(&tour);
+        return vec![tour];
+    }
+    let tour = if route_start.preserve_order {
+        clusters.clone()
+    } else {
+        tsp::solve_with(clusters, cycle_model, max_hop, progress).route
+    };
+    if progress.is_cancelled() {
+        stats.set_route_time(route_time);
+        stats.distance_stats(&tour);
+        return vec![tour];
+    }
+
+    progress.set_phase("partitioning");
+    let mut routes = split_tour(&tour, devices, cycle_model, !route_start.preserve_order);
+    // moving clusters between the sub-routes would change the order that was sent
+    if !route_start.preserve_order {
+        balance(&tour, &mut routes, cycle_model, progress);
+    }
+
+    progress.set_phase("routing devices");
+    let routes: Vec<SingleVec> = routes
+        .into_iter()
+        .enumerate()
+        .map(|(index, route)| {
+            progress.set_steps(index, devices);
+            let route: SingleVec = route.into_iter().map(|i| tour[i]).collect();
+            if progress.is_cancelled() {
+                route
+            } else {
+                solve(&route, &Progress::default())
+            }
+        })
+        .collect();
+    let routes: Vec<SingleVec> = routes
+        .into_iter()
+        .map(|route| stats.fill_long_hops(route))
+        .collect();
+
+    stats.set_route_time(route_time);
+    stats.device_stats(&routes);
+    log::info!(
+        "split {} clusters into {} device routes | imbalance: {:.2}%",
+        clusters.len(),
+        routes.len(),
+        stats.device_imbalance * 100.
+    );
+
+    routes
+}
@@ -1,15 +1,39 @@
 use crate::plugin::{JoinFunction, Plugin};
 use crate::utils;
 use geo::{Distance, Haversine, Point};
-use model::api::{point_array::PointArray, single_vec::SingleVec};
+use model::api::{Precision, point_array::PointArray, single_vec::SingleVec};
 use s2::cellid::CellID;
 use s2::latlng::LatLng;
 use std::collections::HashMap;
 use std::time::Instant;
 
-pub fn join(plugin: &Plugin, input: Vec<SingleVec>) -> SingleVec {
+/// Points of a cell closest to each neighboring cell, the route edges touching them are tried as seams
+const CANDIDATES: usize = 8;
+/// How many points around a seam the 2-opt pass reaches
+const SEAM_WINDOW: usize = 25;
+/// Hard cap on 2-opt passes over the seams
+const SEAM_PASSES: usize = 10;
+/// Minimum gain (in meters) for a move to be applied
+const EPSILON: Precision = 1e-7;
+
+fn dist(a: &PointArray, b: &PointArray) -> Precision {
+    Haversine.distance(Point::new(a[1], a[0]), Point::new(b[1], b[0]))
+}
+
+fn closed_distance(route: &SingleVec) -> Precision {
+    route
+        .iter()
+        .zip(route.iter().cycle().skip(1))
+        .map(|(a, b)| dist(a, b))
+        .sum()
+}
+
+/// Joins the routes of every cell into one closed route, returning it with the meters the
+/// stitching saved over rotating each cell to the point nearest the next one.
+/// Open routes are solved natively so they never get here
+pub fn join(plugin: &Plugin, input: Vec<SingleVec>) -> (SingleVec, Precision) {
     if plugin.split_level == 0 || input.len() < 3 {
-        return input.into_iter().flatten().collect();
+        return (input.into_iter().flatten().collect(), 0.);
     }
     let time = Instant::now();
     let mut point_map = HashMap::<u64, SingleVec>::new();
@@ -35,11 +59,33 @@ pub fn join(plugin: &Plugin, input: Vec<SingleVec>) -> SingleVec {
             point_map.remove(&hash)
         })
         .collect();
+    if clusters.len() < 2 {
+        return (clusters.into_iter().flatten().collect(), 0.);
+    }
 
+    let rotated = rotate(&clusters);
+    let stitched = stitch(&clusters);
+    let before = closed_distance(&rotated);
+    let after = closed_distance(&stitched);
+    log::info!(
+        "joined {} routes in {}ms | stitching saved {:.0}m",
+        stitched.len(),
+        time.elapsed().as_millis(),
+        (before - after).max(0.)
+    );
+    if after < before {
+        (stitched, before - after)
+    } else {
+        (rotated, 0.)
+    }
+}
+
+/// Rotates every cell to the point nearest the next cell and concatenates them
+fn rotate(clusters: &[SingleVec]) -> SingleVec {
     let mut final_routes: SingleVec = vec![];
 
     let last = clusters.len() - 1;
-    for (i, current) in clusters.clone().iter_mut().enumerate() {
+    for (i, current) in clusters.to_vec().iter_mut().enumerate() {
         let next: &SingleVec = if i == last {
             clusters[0].as_ref()
         } else {
@@ -50,10 +96,8 @@ pub fn join(plugin: &Plugin, input: Vec<SingleVec>) -> SingleVec {
         let mut shortest_current_index = 0;
 
         for (current_index, current_point) in current.iter().enumerate() {
-            let current_point = Point::new(current_point[1], current_point[0]);
-            for (_next_index, next_point) in next.iter().enumerate() {
-                let next_point = Point::new(next_point[1], next_point[0]);
-                let distance = Haversine.distance(current_point, next_point);
+            for next_point in next.iter() {
+                let distance = dist(current_point, next_point);
                 if distance < shortest {
                     shortest = distance;
                     shortest_current_index = current_index;
@@ -63,10 +107,192 @@ pub fn join(plugin: &Plugin, input: Vec<SingleVec>) -> SingleVec {
         current.rotate_left(shortest_current_index);
         final_routes.append(current);
     }
-    log::info!(
-        "joined {} routes in {}ms",
-        final_routes.len(),
-        time.elapsed().as_millis()
-    );
     final_routes
 }
+
+/// A way through a cell: the closed cell route is cut at one edge and walked from `enter` to `exit`
+struct Seam {
+    enter: usize,
+    exit: usize,
+    forward: bool,
+    /// Length of the removed edge, subtracted from the cell route
+    cost: Precision,
+}
+
+impl Seam {
+    fn route(&self, cell: &SingleVec) -> SingleVec {
+        let mut route = cell.clone();
+        if self.forward {
+            route.rotate_left(self.enter);
+        } else {
+            route.rotate_left(self.exit);
+            route.reverse();
+        }
+        route
+    }
+}
+
+/// Cuts the cell route at the edges next to the points closest to its neighboring cells, both ways
+fn seams(cell: &SingleVec, neighbors: [PointArray; 2]) -> Vec<Seam> {
+    let len = cell.len();
+    if len < 2 {
+        return vec![Seam {
+            enter: 0,
+            exit: 0,
+            forward: true,
+            cost: 0.,
+        }];
+    }
+    let mut edges: Vec<usize> = neighbors
+        .iter()
+        .flat_map(|neighbor| {
+            let mut closest: Vec<usize> = (0..len).collect();
+            closest.sort_by(|a, b| dist(&cell[*a], neighbor).total_cmp(&dist(&cell[*b], neighbor)));
+            closest.truncate(CANDIDATES);
+            closest
+        })
+        .flat_map(|point| [(point + len - 1) % len, point])
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+
+    edges
+        .into_iter()
+        .flat_map(|edge| {
+            let next = (edge + 1) % len;
+            let cost = -dist(&cell[edge], &cell[next]);
+            [
+                Seam {
+                    enter: next,
+                    exit: edge,
+                    forward: true,
+                    cost,
+                },
+                Seam {
+                    enter: edge,
+                    exit: next,
+                    forward: false,
+                    cost,
+                },
+            ]
+        })
+        .collect()
+}
+
+/// Picks where the route leaves every cell and enters the next one, possibly walking a cell backwards,
+/// so that the seams plus the cut cell routes are as short as possible, then runs a bounded 2-opt over the seams
+fn stitch(clusters: &[SingleVec]) -> SingleVec {
+    let count = clusters.len();
+    let centroids: Vec<PointArray> = clusters.iter().map(|cell| utils::centroid(cell)).collect();
+    let seams: Vec<Vec<Seam>> = clusters
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            seams(
+                cell,
+                [
+                    centroids[(i + count - 1) % count],
+                    centroids[(i + 1) % count],
+                ],
+            )
+        })
+        .collect();
+    // hop from every seam of a cell to every seam of the next one
+    let hops: Vec<Vec<Vec<Precision>>> = (0..count)
+        .map(|i| {
+            let next = (i + 1) % count;
+            seams[i]
+                .iter()
+                .map(|from| {
+                    seams[next]
+                        .iter()
+                        .map(|to| dist(&clusters[i][from.exit], &clusters[next][to.enter]))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    // the cells form a loop, so every seam of the first cell is tried as the fixed start
+    let mut best_length = Precision::MAX;
+    let mut best_choice = vec![0; count];
+    for first in 0..seams[0].len() {
+        let mut lengths = vec![Precision::INFINITY; seams[0].len()];
+        lengths[first] = seams[0][first].cost;
+        let mut parents: Vec<Vec<usize>> = vec![vec![]];
+        for i in 1..count {
+            let (next_lengths, next_parents) = seams[i]
+                .iter()
+                .enumerate()
+                .map(|(to, seam)| {
+                    (0..lengths.len())
+                        .map(|from| (lengths[from] + hops[i - 1][from][to] + seam.cost, from))
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap_or((Precision::MAX, 0))
+                })
+                .unzip();
+            lengths = next_lengths;
+            parents.push(next_parents);
+        }
+        let Some((last, length)) = lengths
+            .iter()
+            .enumerate()
+            .map(|(last, length)| (last, length + hops[count - 1][last][first]))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+        else {
+            continue;
+        };
+        if length < best_length {
+            best_length = length;
+            best_choice[count - 1] = last;
+            for i in (1..count).rev() {
+                best_choice[i - 1] = parents[i][best_choice[i]];
+            }
+        }
+    }
+
+    let mut seam_positions = vec![];
+    let mut route: SingleVec = vec![];
+    for (i, cell) in clusters.iter().enumerate() {
+        seam_positions.push(route.len());
+        route.extend(seams[i][best_choice[i]].route(cell));
+    }
+    two_opt_seams(&mut route, &seam_positions);
+    route
+}
+
+/// 2-opt restricted to segments that start within [SEAM_WINDOW] points of a seam
+fn two_opt_seams(route: &mut SingleVec, seam_positions: &[usize]) {
+    let len = route.len();
+    if len < 4 {
+        return;
+    }
+    // the seam at position 0 closes the loop, turning the route by half puts it in the middle
+    // so the segments around it can be reversed as well
+    let shift = len / 2;
+    route.rotate_left(shift);
+    let seam_positions: Vec<usize> = seam_positions
+        .iter()
+        .map(|seam| (seam + len - shift) % len)
+        .collect();
+    for _ in 0..SEAM_PASSES {
+        let mut improved = false;
+        for seam in &seam_positions {
+            for i in seam.saturating_sub(SEAM_WINDOW)..(seam + SEAM_WINDOW).min(len) {
+                for j in i + 2..(i + SEAM_WINDOW).min(len - 1) {
+                    let gain = dist(&route[i], &route[i + 1]) + dist(&route[j], &route[j + 1])
+                        - dist(&route[i], &route[j])
+                        - dist(&route[i + 1], &route[j + 1]);
+                    if gain > EPSILON {
+                        route[i + 1..=j].reverse();
+                        improved = true;
+                    }
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    route.rotate_right(shift);
+}
@@ -1,18 +1,25 @@
-use std::time::Instant;
+use std::{cell::Cell, collections::BTreeMap, time::Instant};
 
-use model::api::{single_vec::SingleVec, sort_by::SortBy};
+use model::api::{
+    args::RouteStart, point_array::PointArray, single_vec::SingleVec, sort_by::SortBy,
+};
 
-use self::sorting::{SortGeohash, SortLatLng, SortPointCount, SortRandom, SortS2};
+use self::sorting::SortS2;
 use crate::{
-    plugin::{Folder, Plugin},
+    plugin::{Folder, Plugin, discovery, manifest::Manifest},
+    progress::Progress,
     stats::Stats,
+    strategy::{self, RouteInput},
     utils,
 };
 
+pub mod devices;
 mod join;
 pub mod sorting;
-// pub mod vrp;
+pub(crate) mod strategies;
+pub mod tsp;
 
+#[allow(clippy::too_many_arguments)]
 pub fn main(
     data_points: &SingleVec,
     clusters: SingleVec,
@@ -21,50 +28,192 @@ pub fn main(
     radius: f64,
     stats: &mut Stats,
     routing_args: &str,
+    seed: u64,
+    route_start: &RouteStart,
+    progress: &Progress,
 ) -> SingleVec {
+    if progress.is_cancelled() {
+        log::warn!("routing was cancelled");
+        return clusters;
+    }
     let route_time = Instant::now();
-    let clusters = match sort_by {
-        SortBy::PointCount => clusters.sort_point_count(&data_points, radius),
-        SortBy::LatLon => clusters.sort_lat_lng(),
-        SortBy::GeoHash => clusters.sort_geohash(),
-        SortBy::S2Cell => clusters.sort_s2(),
-        SortBy::Random => clusters.sort_random(),
-        SortBy::Unset => clusters,
-        SortBy::Custom(plugin) => {
-            let clusters = clusters.sort_s2();
-            match Plugin::new(plugin, Folder::Routing, route_split_level, routing_args) {
-                Ok(plugin_manager) => match plugin_manager.run_multi(&clusters, Some(join::join)) {
-                    Ok(sorted_clusters) => sorted_clusters,
-                    Err(e) => {
-                        log::error!("Error while running plugin: {}", e);
-                        clusters
-                    }
-                },
+    progress.set_phase("routing");
+    let start = route_start.point(&clusters);
+    stats.set_open_route(route_start.open);
+    if route_start.open {
+        let end = route_start.end_point(&clusters);
+        let clusters = if route_start.preserve_order {
+            clusters
+        } else {
+            native_path(&clusters, start, end, stats, progress)
+        };
+        let clusters = stats.fill_long_hops(clusters);
+        stats.set_route_time(route_time);
+        stats.distance_stats(&clusters);
+        return clusters;
+    }
+    let clusters = if route_start.preserve_order {
+        clusters
+    } else {
+        sort(
+            clusters,
+            data_points,
+            sort_by,
+            route_split_level,
+            radius,
+            stats,
+            routing_args,
+            seed,
+            progress,
+        )
+    };
+    let clusters = match start {
+        Some(start) => utils::rotate_to_point(clusters, start),
+        None if route_start.preserve_order => clusters,
+        None => utils::rotate_to_best(clusters, stats),
+    };
+    let clusters = stats.fill_long_hops(clusters);
+
+    stats.set_route_time(route_time);
+    stats.distance_stats(&clusters);
+
+    clusters
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sort(
+    clusters: SingleVec,
+    data_points: &SingleVec,
+    sort_by: &SortBy,
+    route_split_level: u64,
+    radius: f64,
+    stats: &mut Stats,
+    routing_args: &str,
+    seed: u64,
+    progress: &Progress,
+) -> SingleVec {
+    let strategy_name = match sort_by {
+        SortBy::Unset => return clusters,
+        SortBy::PointCount => "point_count",
+        SortBy::LatLon => "latlon",
+        SortBy::GeoHash => "geohash",
+        SortBy::S2Cell => "s2",
+        SortBy::Random => "random",
+        SortBy::NativeTsp => "native_tsp",
+        SortBy::Custom(plugin) => plugin,
+    };
+    if let Some(strategy) = strategy::ROUTING.get(strategy_name) {
+        return strategy.route(
+            clusters,
+            &RouteInput {
+                data_points,
+                radius,
+                route_split_level,
+                args: routing_args,
+                seed,
+                progress,
+            },
+            stats,
+        );
+    }
+    let clusters = clusters.sort_s2();
+    match Plugin::new(
+        strategy_name,
+        Folder::Routing,
+        route_split_level,
+        routing_args,
+    ) {
+        Ok(mut plugin_manager) => {
+            plugin_manager
+                .set_radius(radius)
+                .set_progress(progress.clone());
+            let stitch_saved = Cell::new(0.);
+            let joined = plugin_manager.run_multi(
+                &clusters,
+                Some(|plugin: &Plugin, input: Vec<SingleVec>| {
+                    let (route, saved) = join::join(plugin, input);
+                    stitch_saved.set(saved);
+                    route
+                }),
+            );
+            stats.set_stitch_saved(stitch_saved.get());
+            stats.add_plugin_report(plugin_manager.report());
+            match joined {
+                Ok(sorted_clusters) => sorted_clusters,
                 Err(e) => {
-                    log::error!("Plugin not found: {}", e);
+                    log::error!("Error while running plugin: {}", e);
                     clusters
                 }
             }
         }
-    };
-    let clusters = utils::rotate_to_best(clusters, stats);
+        Err(e) if strategy_name == "tsp" => {
+            log::warn!("{}, falling back to the native TSP solver", e);
+            native_tsp(&clusters, stats, progress)
+        }
+        Err(e) => {
+            log::error!("Plugin not found: {}", e);
+            clusters
+        }
+    }
+}
 
-    stats.set_route_time(route_time);
-    stats.distance_stats(&clusters);
+/// Minimizes the cycle time instead of the distance when the cycle model of the stats asks for it
+/// and avoids hops above its hop limit
+fn native_tsp(clusters: &SingleVec, stats: &mut Stats, progress: &Progress) -> SingleVec {
+    let cycle_model = stats.cycle_model().clone();
+    let solution = tsp::solve_with(
+        clusters,
+        cycle_model.minimize.then_some(&cycle_model),
+        stats.hop_limit().map(|hop_limit| hop_limit.max_distance),
+        progress,
+    );
+    // the saved distance is reported in meters
+    if !solution.timed {
+        stats.set_seed_distance(solution.seed_distance);
+    }
+    solution.route
+}
 
-    clusters
+/// Open routes are always solved natively as a Hamiltonian path, from and to the clusters closest to `start` and `end`
+fn native_path(
+    clusters: &SingleVec,
+    start: Option<PointArray>,
+    end: Option<PointArray>,
+    stats: &mut Stats,
+    progress: &Progress,
+) -> SingleVec {
+    let cycle_model = stats.cycle_model().clone();
+    let solution = tsp::solve_path(
+        clusters,
+        start.map(|start| utils::closest(clusters, start)),
+        end.map(|end| utils::closest(clusters, end)),
+        cycle_model.minimize.then_some(&cycle_model),
+        stats.hop_limit().map(|hop_limit| hop_limit.max_distance),
+        progress,
+    );
+    if !solution.timed {
+        stats.set_seed_distance(solution.seed_distance);
+    }
+    solution.route
 }
 
 pub fn routing_plugins() -> Vec<String> {
-    utils::get_plugin_list("algorithms/src/routing/plugins").unwrap_or(vec![])
+    discovery::plugins(Folder::Routing)
+}
+
+pub fn routing_plugin_manifests() -> BTreeMap<String, Manifest> {
+    Manifest::all(Folder::Routing)
+}
+
+/// Registered strategies that don't ship with Kōji, followed by the plugins
+pub fn custom_routing_options() -> Vec<String> {
+    let mut options = strategy::ROUTING.custom_names();
+    options.extend(routing_plugins());
+    options
 }
 
 pub fn all_routing_options() -> Vec<String> {
-    let mut options = routing_plugins();
-    options.push("point_count".to_string());
-    options.push("latlon".to_string());
-    options.push("geohash".to_string());
-    options.push("s2".to_string());
-    options.push("random".to_string());
+    let mut options = custom_routing_options();
+    options.extend(strategy::ROUTING.builtin_names());
     options
 }
@@ -11,19 +11,19 @@ use s2::{cellid::CellID, latlng::LatLng};
 use crate::rtree::{self, cluster, point};
 
 pub trait SortRandom {
-    fn sort_random(self) -> Self;
-    fn sort_random_mut(&mut self);
+    fn sort_random(self, seed: u64) -> Self;
+    fn sort_random_mut(&mut self, seed: u64);
 }
 
 impl SortRandom for SingleVec {
-    fn sort_random(self) -> Self {
+    fn sort_random(self, seed: u64) -> Self {
         let mut clusters = self;
-        clusters.sort_random_mut();
+        clusters.sort_random_mut(seed);
         clusters
     }
 
-    fn sort_random_mut(&mut self) {
-        let mut rng = SmallRng::seed_from_u64(42);
+    fn sort_random_mut(&mut self, seed: u64) {
+        let mut rng = SmallRng::seed_from_u64(seed);
         self.shuffle(&mut rng);
     }
 }
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use model::api::single_vec::SingleVec;
+
+use super::sorting::{SortGeohash, SortLatLng, SortPointCount, SortRandom, SortS2};
+use crate::{
+    stats::Stats,
+    strategy::{RouteInput, RouteStrategy},
+};
+
+pub(crate) fn builtins() -> Vec<(&'static str, Arc<dyn RouteStrategy>)> {
+    vec![
+        ("point_count", Arc::new(PointCount)),
+        ("latlon", Arc::new(LatLon)),
+        ("geohash", Arc::new(GeoHash)),
+        ("s2", Arc::new(S2Cell)),
+        ("random", Arc::new(Random)),
+        ("native_tsp", Arc::new(NativeTsp)),
+    ]
+}
+
+struct PointCount;
+
+impl RouteStrategy for PointCount {
+    fn route(&self, clusters: SingleVec, input: &RouteInput, _stats: &mut Stats) -> SingleVec {
+        clusters.sort_point_count(input.data_points, input.radius)
+    }
+}
+
+struct LatLon;
+
+impl RouteStrategy for LatLon {
+    fn route(&self, clusters: SingleVec, _input: &RouteInput, _stats: &mut Stats) -> SingleVec {
+        clusters.sort_lat_lng()
+    }
+}
+
+struct GeoHash;
+
+impl RouteStrategy for GeoHash {
+    fn route(&self, clusters: SingleVec, _input: &RouteInput, _stats: &mut Stats) -> SingleVec {
+        clusters.sort_geohash()
+    }
+}
+
+struct S2Cell;
+
+impl RouteStrategy for S2Cell {
+    fn route(&self, clusters: SingleVec, _input: &RouteInput, _stats: &mut Stats) -> SingleVec {
+        clusters.sort_s2()
+    }
+}
+
+struct Random;
+
+impl RouteStrategy for Random {
+    fn route(&self, clusters: SingleVec, input: &RouteInput, _stats: &mut Stats) -> SingleVec {
+        clusters.sort_random(input.seed)
+    }
+}
+
+struct NativeTsp;
+
+impl RouteStrategy for NativeTsp {
+    fn route(&self, clusters: SingleVec, input: &RouteInput, stats: &mut Stats) -> SingleVec {
+        super::native_tsp(&clusters, stats, input.progress)
+    }
+}
@@ -0,0 +1,554 @@
+use std::time::Instant;
+
+use geo::{Distance, Haversine, Point};
+use model::api::{Precision, single_vec::SingleVec};
+use rstar::{RTree, primitives::GeomWithData};
+
+use super::sorting::SortS2;
+use crate::{progress::Progress, stats::CycleModel};
+
+/// How many of the closest clusters are considered as candidates for each local search move
+const NEIGHBORS: usize = 10;
+/// Hard cap on full improvement passes over the route
+const MAX_PASSES: usize = 50;
+/// Longest segment that Or-opt will try to relocate
+const MAX_SEGMENT: usize = 3;
+/// Minimum gain (in meters) for a move to be applied, guards against float noise loops
+const EPSILON: Precision = 1e-7;
+/// A hop above the max hop distance costs this many max length hops on top of its own length
+const LONG_HOP_PENALTY: Precision = 10.;
+
+pub struct Solution {
+    pub route: SingleVec,
+    /// Length of the space-filling-curve seed route, before local search
+    pub seed_distance: Precision,
+    /// Length of the returned route
+    pub distance: Precision,
+    /// Whether the lengths are in seconds of cycle time instead of meters
+    pub timed: bool,
+}
+
+pub trait SortTsp {
+    fn sort_tsp(self) -> Self;
+    fn sort_tsp_mut(&mut self);
+}
+
+impl SortTsp for SingleVec {
+    fn sort_tsp(self) -> Self {
+        solve(&self, &Progress::default()).route
+    }
+
+    fn sort_tsp_mut(&mut self) {
+        *self = solve(self, &Progress::default()).route;
+    }
+}
+
+/// Turns the tour into a Hamiltonian path: a dummy city sits between the two ends of the path,
+/// free to connect to any city, or only to the fixed ends without paying the penalty
+struct Ends {
+    dummy: usize,
+    start: Option<usize>,
+    end: Option<usize>,
+    penalty: Precision,
+}
+
+impl Ends {
+    fn dist(&self, city: usize) -> Precision {
+        if (self.start.is_none() && self.end.is_none())
+            || self.start == Some(city)
+            || self.end == Some(city)
+        {
+            0.
+        } else {
+            self.penalty
+        }
+    }
+}
+
+struct Tour<'a> {
+    points: &'a SingleVec,
+    cycle_model: Option<&'a CycleModel>,
+    max_hop: Option<Precision>,
+    ends: Option<Ends>,
+    order: Vec<usize>,
+    pos: Vec<usize>,
+}
+
+impl<'a> Tour<'a> {
+    fn new(
+        points: &'a SingleVec,
+        cycle_model: Option<&'a CycleModel>,
+        max_hop: Option<Precision>,
+        order: Vec<usize>,
+    ) -> Self {
+        let mut tour = Self {
+            points,
+            cycle_model,
+            max_hop,
+            ends: None,
+            pos: vec![0; order.len()],
+            order,
+        };
+        tour.update_positions();
+        tour
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn update_positions(&mut self) {
+        for (i, city) in self.order.iter().enumerate() {
+            self.pos[*city] = i;
+        }
+    }
+
+    fn meters(&self, a: usize, b: usize) -> Precision {
+        let a = self.points[a];
+        let b = self.points[b];
+        Haversine.distance(Point::new(a[1], a[0]), Point::new(b[1], b[0]))
+    }
+
+    /// The meters themselves, or the seconds a hop that long takes when solving for cycle time
+    fn cost(&self, meters: Precision) -> Precision {
+        match self.cycle_model {
+            Some(cycle_model) => cycle_model.hop_time(meters),
+            None => meters,
+        }
+    }
+
+    /// Length of the hop as it is reported
+    fn hop(&self, a: usize, b: usize) -> Precision {
+        self.cost(self.meters(a, b))
+    }
+
+    /// Length of the hop as it is minimized, with hops above the max hop distance penalized
+    fn dist(&self, a: usize, b: usize) -> Precision {
+        if let Some(ends) = &self.ends
+            && (a == ends.dummy || b == ends.dummy)
+        {
+            return ends.dist(if a == ends.dummy { b } else { a });
+        }
+        let meters = self.meters(a, b);
+        match self.max_hop {
+            Some(max_hop) if meters > max_hop => {
+                self.cost(meters) + LONG_HOP_PENALTY * self.cost(max_hop)
+            }
+            _ => self.cost(meters),
+        }
+    }
+
+    fn next(&self, city: usize) -> usize {
+        self.order[(self.pos[city] + 1) % self.len()]
+    }
+
+    fn prev(&self, city: usize) -> usize {
+        self.order[(self.pos[city] + self.len() - 1) % self.len()]
+    }
+
+    fn distance(&self) -> Precision {
+        self.order
+            .iter()
+            .map(|city| self.hop(*city, self.next(*city)))
+            .sum()
+    }
+
+    /// Length of the route without the hop from its last city back to the first
+    fn path_distance(&self, order: &[usize]) -> Precision {
+        order.windows(2).map(|hop| self.hop(hop[0], hop[1])).sum()
+    }
+
+    /// Cuts the tour open at the dummy city, facing the fixed start or end the right way
+    fn path(&self) -> Vec<usize> {
+        let Some(ends) = &self.ends else {
+            return self.order.clone();
+        };
+        let at = self.pos[ends.dummy];
+        let mut order: Vec<usize> = self.order[at + 1..]
+            .iter()
+            .chain(self.order[..at].iter())
+            .copied()
+            .collect();
+        let reversed = match (ends.start, ends.end) {
+            (Some(start), _) => order.first() != Some(&start),
+            (None, Some(end)) => order.last() != Some(&end),
+            (None, None) => false,
+        };
+        if reversed {
+            order.reverse();
+        }
+        order
+    }
+
+    /// Reverses the path between `from` and `to` (inclusive, walking forward).
+    /// Reversing the complement of a segment yields the same cycle, so the shorter side is used.
+    fn reverse(&mut self, from: usize, to: usize) {
+        let n = self.len();
+        let (i, j) = (self.pos[from], self.pos[to]);
+        let inner = (j + n - i) % n + 1;
+        let (mut i, mut j, steps) = if inner * 2 <= n {
+            (i, j, inner / 2)
+        } else {
+            ((j + 1) % n, (i + n - 1) % n, (n - inner) / 2)
+        };
+        for _ in 0..steps {
+            self.order.swap(i, j);
+            self.pos[self.order[i]] = i;
+            self.pos[self.order[j]] = j;
+            i = (i + 1) % n;
+            j = (j + n - 1) % n;
+        }
+    }
+
+    /// Classic 2-opt restricted to each city's nearest neighbors
+    fn two_opt(&mut self, a: usize, neighbors: &[usize]) -> bool {
+        for (succ, b) in [(true, self.next(a)), (false, self.prev(a))] {
+            let d_ab = self.dist(a, b);
+            for &c in neighbors {
+                let d_ac = self.dist(a, c);
+                if d_ac >= d_ab {
+                    break;
+                }
+                let d = if succ { self.next(c) } else { self.prev(c) };
+                if c == b || d == a {
+                    continue;
+                }
+                let gain = d_ab + self.dist(c, d) - d_ac - self.dist(b, d);
+                if gain > EPSILON {
+                    if succ {
+                        self.reverse(b, c);
+                    } else {
+                        self.reverse(c, b);
+                    }
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Moves a short segment starting at `a` next to one of its neighbors, optionally reversed
+    fn or_opt(&mut self, a: usize, neighbors: &[usize]) -> bool {
+        let n = self.len();
+        for length in 1..=MAX_SEGMENT.min(n.saturating_sub(3)) {
+            let start = self.pos[a];
+            let segment: Vec<usize> = (0..length).map(|k| self.order[(start + k) % n]).collect();
+            let first = segment[0];
+            let last = segment[length - 1];
+            let before = self.prev(first);
+            let after = self.next(last);
+            let removal_gain =
+                self.dist(before, first) + self.dist(last, after) - self.dist(before, after);
+            if removal_gain <= EPSILON {
+                continue;
+            }
+            for &c in neighbors {
+                if segment.contains(&c) {
+                    continue;
+                }
+                for (left, right) in [(c, self.next(c)), (self.prev(c), c)] {
+                    if segment.contains(&left) || segment.contains(&right) {
+                        continue;
+                    }
+                    let d_lr = self.dist(left, right);
+                    let forward = self.dist(left, first) + self.dist(last, right) - d_lr;
+                    let reversed = self.dist(left, last) + self.dist(first, right) - d_lr;
+                    let (cost, reverse) = if reversed < forward {
+                        (reversed, true)
+                    } else {
+                        (forward, false)
+                    };
+                    if removal_gain - cost > EPSILON {
+                        self.move_segment(&segment, left, reverse);
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn move_segment(&mut self, segment: &[usize], left: usize, reverse: bool) {
+        let mut order: Vec<usize> = self
+            .order
+            .iter()
+            .filter(|city| !segment.contains(city))
+            .copied()
+            .collect();
+        let insert_at = order.iter().position(|city| *city == left).unwrap_or(0) + 1;
+        if reverse {
+            order.splice(insert_at..insert_at, segment.iter().rev().copied());
+        } else {
+            order.splice(insert_at..insert_at, segment.iter().copied());
+        }
+        self.order = order;
+        self.update_positions();
+    }
+}
+
+fn neighbor_lists(points: &SingleVec) -> Vec<Vec<usize>> {
+    let lat_scale = points.iter().map(|p| p[0]).sum::<Precision>() / points.len() as Precision;
+    let lat_scale = lat_scale.to_radians().cos();
+    let project = |p: &[Precision; 2]| [p[1] * lat_scale, p[0]];
+
+    let tree = RTree::bulk_load(
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| GeomWithData::new(project(p), i))
+            .collect(),
+    );
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let mut neighbors: Vec<(Precision, usize)> = tree
+                .nearest_neighbor_iter(&project(p))
+                .filter(|n| n.data != i)
+                .take(NEIGHBORS)
+                .map(|n| {
+                    let other = points[n.data];
+                    (
+                        Haversine.distance(Point::new(p[1], p[0]), Point::new(other[1], other[0])),
+                        n.data,
+                    )
+                })
+                .collect();
+            neighbors.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            neighbors.into_iter().map(|(_, n)| n).collect()
+        })
+        .collect()
+}
+
+/// Seeds a route along the S2 (Hilbert) curve, then improves it with 2-opt and Or-opt
+/// until no improving move is left, [MAX_PASSES] is reached or the progress is cancelled
+pub fn solve(points: &SingleVec, progress: &Progress) -> Solution {
+    solve_with(points, None, None, progress)
+}
+
+/// Same as [solve], minimizing the cycle time of the [CycleModel] instead of the distance when one is passed.
+/// With a `max_hop` (in meters) longer hops are avoided wherever a detour is cheaper than the penalty
+pub fn solve_with(
+    points: &SingleVec,
+    cycle_model: Option<&CycleModel>,
+    max_hop: Option<Precision>,
+    progress: &Progress,
+) -> Solution {
+    let time = Instant::now();
+    let timed = cycle_model.is_some();
+    if points.len() < 4 {
+        let distance =
+            Tour::new(points, cycle_model, max_hop, (0..points.len()).collect()).distance();
+        return Solution {
+            route: points.clone(),
+            seed_distance: distance,
+            distance,
+            timed,
+        };
+    }
+
+    let seed = points.clone().sort_s2();
+    let neighbors = neighbor_lists(&seed);
+    let mut tour = Tour::new(&seed, cycle_model, max_hop, (0..seed.len()).collect());
+    let seed_distance = tour.distance();
+
+    let passes = improve(&mut tour, &neighbors, progress);
+    let distance = tour.distance();
+    log_solution(
+        "tsp",
+        seed.len(),
+        time,
+        passes,
+        seed_distance,
+        distance,
+        timed,
+    );
+
+    Solution {
+        route: tour.order.iter().map(|i| seed[*i]).collect(),
+        seed_distance,
+        distance,
+        timed,
+    }
+}
+
+/// Same as [solve_with] for a one-way route: finds the shortest Hamiltonian path, optionally
+/// starting and/or ending at the points with the given indices. Distances leave out the hop back to the start
+pub fn solve_path(
+    points: &SingleVec,
+    start: Option<usize>,
+    end: Option<usize>,
+    cycle_model: Option<&CycleModel>,
+    max_hop: Option<Precision>,
+    progress: &Progress,
+) -> Solution {
+    let time = Instant::now();
+    let timed = cycle_model.is_some();
+    let end = end.filter(|end| Some(*end) != start);
+    if points.len() < 3 {
+        let order = path_seed(points.len(), start, end);
+        let distance = Tour::new(points, cycle_model, max_hop, order.clone()).path_distance(&order);
+        return Solution {
+            route: order.iter().map(|i| points[*i]).collect(),
+            seed_distance: distance,
+            distance,
+            timed,
+        };
+    }
+
+    let seed = points.clone().sort_s2();
+    let position = |index: Option<usize>| {
+        index.and_then(|index| seed.iter().position(|point| *point == points[index]))
+    };
+    let start = position(start);
+    // duplicate points can map both ends onto the same seed index
+    let end = position(end).filter(|end| Some(*end) != start);
+    let dummy = seed.len();
+
+    let mut order = path_seed(seed.len(), start, end);
+    order.push(dummy);
+    let mut tour = Tour::new(&seed, cycle_model, max_hop, order);
+    let seed_distance = tour.path_distance(&tour.order[..dummy]);
+    // a valid path never costs as much as the seed loop, so the local search can't trade the fixed ends away
+    let penalty = (0..dummy)
+        .map(|i| tour.dist(tour.order[i], tour.order[(i + 1) % dummy]))
+        .sum::<Precision>()
+        + 1.;
+    tour.ends = Some(Ends {
+        dummy,
+        start,
+        end,
+        penalty,
+    });
+
+    let mut neighbors = neighbor_lists(&seed);
+    let free: Vec<usize> = (0..dummy)
+        .filter(|city| tour.dist(*city, dummy) == 0.)
+        .collect();
+    for city in free.iter() {
+        neighbors[*city].insert(0, dummy);
+    }
+    neighbors.push(free);
+
+    let passes = improve(&mut tour, &neighbors, progress);
+    let order = tour.path();
+    let distance = tour.path_distance(&order);
+    log_solution(
+        "path",
+        seed.len(),
+        time,
+        passes,
+        seed_distance,
+        distance,
+        timed,
+    );
+
+    Solution {
+        route: order.iter().map(|i| seed[*i]).collect(),
+        seed_distance,
+        distance,
+        timed,
+    }
+}
+
+/// Keeps the seed order, rotated to the start with the end moved last
+fn path_seed(len: usize, start: Option<usize>, end: Option<usize>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    if let Some(start) = start {
+        order.rotate_left(start);
+    }
+    if let Some(end) = end {
+        order.retain(|city| *city != end);
+        order.push(end);
+    }
+    order
+}
+
+/// Applies 2-opt and Or-opt moves until no improving move is left, [MAX_PASSES] is reached
+/// or the progress is cancelled, returns the number of passes
+fn improve(tour: &mut Tour, neighbors: &[Vec<usize>], progress: &Progress) -> usize {
+    let mut passes = 0;
+    let mut improved = true;
+    while improved && passes < MAX_PASSES && !progress.is_cancelled() {
+        progress.set_steps(passes, MAX_PASSES);
+        improved = false;
+        passes += 1;
+        for (city, neighbors) in neighbors.iter().enumerate() {
+            while tour.two_opt(city, neighbors) || tour.or_opt(city, neighbors) {
+                improved = true;
+            }
+        }
+    }
+    passes
+}
+
+fn log_solution(
+    kind: &str,
+    points: usize,
+    time: Instant,
+    passes: usize,
+    seed_distance: Precision,
+    distance: Precision,
+    timed: bool,
+) {
+    let unit = if timed { "s" } else { "m" };
+    log::info!(
+        "native {} solved {} points in {:.2}s after {} passes | seed: {:.0}{} | final: {:.0}{}",
+        kind,
+        points,
+        time.elapsed().as_secs_f32(),
+        passes,
+        seed_distance,
+        unit,
+        distance,
+        unit,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scattered points in a few hundred meters, the same on every run
+    fn points(count: usize) -> SingleVec {
+        let mut state: u64 = 42;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as Precision / (1u64 << 31) as Precision
+        };
+        (0..count)
+            .map(|_| [40. + next() * 0.01, -74. + next() * 0.01])
+            .collect()
+    }
+
+    fn sorted(points: &SingleVec) -> SingleVec {
+        let mut points = points.clone();
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]).then_with(|| a[1].total_cmp(&b[1])));
+        points
+    }
+
+    #[test]
+    fn route_is_a_permutation() {
+        let points = points(200);
+        let solution = solve(&points, &Progress::default());
+        assert_eq!(sorted(&solution.route), sorted(&points));
+        assert!(solution.distance <= solution.seed_distance + EPSILON);
+    }
+
+    #[test]
+    fn path_keeps_start_and_end() {
+        let points = points(100);
+        for (start, end) in [(Some(7), Some(63)), (Some(12), None), (None, Some(40))] {
+            let solution = solve_path(&points, start, end, None, None, &Progress::default());
+            assert_eq!(sorted(&solution.route), sorted(&points));
+            if let Some(start) = start {
+                assert_eq!(solution.route.first(), Some(&points[start]));
+            }
+            if let Some(end) = end {
+                assert_eq!(solution.route.last(), Some(&points[end]));
+            }
+        }
+    }
+}
@@ -4,7 +4,7 @@ use std::{
 };
 
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use rstar::RTree;
+use rstar::{PointDistance, RTree};
 
 use super::{SortDedupe, point::Point};
 
@@ -46,7 +46,11 @@ impl<'a> Cluster<'a> {
             .all
             .par_iter()
             .filter_map(|p| {
-                let points = tree.locate_all_at_point(&p.center).count();
+                // the tree can be spawned with a larger radius than the point's own when radii are mixed
+                let points = tree
+                    .locate_all_at_point(&p.center)
+                    .filter(|c| c.distance_2(&p.center) <= p.radius)
+                    .count();
                 if points == 1 { Some(*p) } else { None }
             })
             .collect();
@@ -1,20 +1,152 @@
 pub mod cluster;
 pub mod point;
 
+use hashbrown::HashMap;
 use model::api::{single_vec::SingleVec, Precision};
 use point::Point;
 
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use rstar::RTree;
+use s2::{cellid::CellID, latlng::LatLng};
 
 pub trait SortDedupe {
     fn sort_dedupe(&mut self);
 }
 
+pub trait TotalWeight {
+    fn total_weight(&self) -> Precision;
+}
+
+/// Optional data point weights, keyed by the same level 20 cell that [Point] equality uses,
+/// so they survive the data points being split up or reordered
+#[derive(Debug, Clone, Default)]
+pub struct Weights(HashMap<CellID, Precision>);
+
+impl Weights {
+    /// Pairs `weights` with `points` by index.
+    /// Weights are scaled so the average point weighs `1.0`, which keeps `min_points` comparable to an unweighted run.
+    /// An empty `weights` slice leaves every point at `1.0`
+    pub fn new(points: &SingleVec, weights: &[Precision]) -> Self {
+        if weights.is_empty() {
+            return Self::default();
+        }
+        if weights.len() != points.len() {
+            log::warn!(
+                "received {} weights for {} data points, ignoring the weights",
+                weights.len(),
+                points.len()
+            );
+            return Self::default();
+        }
+        let weights: Vec<Precision> = weights
+            .iter()
+            .map(|w| if w.is_finite() { w.max(0.) } else { 1. })
+            .collect();
+        let total = weights.iter().sum::<Precision>();
+        if total <= 0. {
+            log::warn!("data point weights add up to 0, ignoring the weights");
+            return Self::default();
+        }
+        let scale = points.len() as Precision / total;
+
+        let mut map = HashMap::with_capacity(points.len());
+        for (point, weight) in points.iter().zip(weights) {
+            let cell_id = CellID::from(LatLng::from_degrees(point[0], point[1])).parent(20);
+            let weight = weight * scale;
+            map.entry(cell_id)
+                .and_modify(|w: &mut Precision| *w = w.max(weight))
+                .or_insert(weight);
+        }
+        Self(map)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, cell_id: &CellID) -> Precision {
+        self.0.get(cell_id).copied().unwrap_or(1.)
+    }
+}
+
+/// Optional data point radii, keyed the same way as [Weights].
+/// A point is only covered by a cluster that is within the point's own radius
+#[derive(Debug, Clone, Default)]
+pub struct Radii(HashMap<CellID, Precision>);
+
+impl Radii {
+    /// Pairs `radii` with `points` by index, points without a usable radius fall back to the run's radius
+    pub fn new(points: &SingleVec, radii: &[Precision]) -> Self {
+        if radii.is_empty() {
+            return Self::default();
+        }
+        if radii.len() != points.len() {
+            log::warn!(
+                "received {} radii for {} data points, ignoring the radii",
+                radii.len(),
+                points.len()
+            );
+            return Self::default();
+        }
+        Self(
+            points
+                .iter()
+                .zip(radii)
+                .filter(|(_, radius)| radius.is_finite() && **radius > 0.)
+                .map(|(point, radius)| {
+                    (
+                        CellID::from(LatLng::from_degrees(point[0], point[1])).parent(20),
+                        *radius,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, cell_id: &CellID, default: Precision) -> Precision {
+        self.0.get(cell_id).copied().unwrap_or(default)
+    }
+
+    /// Largest radius in use, clusters need to reach this far to find every point that they may cover
+    pub fn max(&self, default: Precision) -> Precision {
+        self.0.values().copied().fold(default, Precision::max)
+    }
+
+    /// Smallest radius in use, a cluster within this distance covers any point
+    pub fn min(&self, default: Precision) -> Precision {
+        self.0.values().copied().fold(default, Precision::min)
+    }
+}
+
 pub fn spawn(radius: Precision, points: &SingleVec) -> RTree<Point> {
+    spawn_with(radius, points, &Weights::default(), &Radii::default())
+}
+
+/// Spawns a data point tree where each point carries its own weight and radius,
+/// `radius` is used for the points without one
+pub fn spawn_with(
+    radius: Precision,
+    points: &SingleVec,
+    weights: &Weights,
+    radii: &Radii,
+) -> RTree<Point> {
     let points = points
         .iter()
-        .map(|p| Point::new(radius, 20, *p))
+        .map(|p| {
+            let mut point = Point::new(radius, 20, *p);
+            if !radii.is_empty() {
+                let point_radius = radii.get(&point.cell_id, radius);
+                if point_radius != radius {
+                    point = Point::new(point_radius, 20, *p);
+                }
+            }
+            point.weight = weights.get(&point.cell_id);
+            point
+        })
         .collect::<Vec<_>>();
     RTree::bulk_load(points)
 }
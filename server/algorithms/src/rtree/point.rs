@@ -10,11 +10,13 @@ use rayon::slice::ParallelSliceMut;
 use rstar::{AABB, PointDistance, RTreeObject};
 use s2::{cellid::CellID, latlng::LatLng};
 
-use super::{SortDedupe, cluster::Cluster};
+use super::{SortDedupe, TotalWeight, cluster::Cluster};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Point {
     pub radius: Precision,
+    /// How much covering this point is worth, `1.0` unless weights were supplied
+    pub weight: Precision,
     pub center: [Precision; 2],
     pub cell_id: CellID,
     cached_envelope: AABB<[Precision; 2]>,
@@ -28,6 +30,7 @@ impl Point {
 
         Self {
             radius,
+            weight: 1.,
             center,
             cell_id: CellID::from(LatLng::from_degrees(center[0], center[1])).parent(cell_level),
             cached_envelope: AABB::from_corners(
@@ -110,3 +113,9 @@ impl SortDedupe for Vec<&Point> {
         self.dedup_by(|a, b| a.cell_id == b.cell_id);
     }
 }
+
+impl TotalWeight for [&Point] {
+    fn total_weight(&self) -> Precision {
+        self.iter().map(|p| p.weight).sum()
+    }
+}
@@ -11,9 +11,18 @@ use rayon::{
     slice::ParallelSliceMut,
 };
 
-use crate::rtree::{self, SortDedupe};
+use crate::{
+    exclusion::Exclusions,
+    rtree::{self, SortDedupe},
+};
 
-pub fn with_data(radius: Precision, points: &SingleVec, clusters: &SingleVec) -> SingleVec {
+pub fn with_data(
+    radius: Precision,
+    points: &SingleVec,
+    clusters: &SingleVec,
+    seed: u64,
+    exclusions: &Exclusions,
+) -> SingleVec {
     let time = Instant::now();
     log::info!("centering clusters on their points");
     let tree = rtree::spawn(radius, points);
@@ -50,6 +59,7 @@ pub fn with_data(radius: Precision, points: &SingleVec, clusters: &SingleVec) ->
                         .map(|p| geo::Point::new(p.center[1], p.center[0])),
                     radius,
                     100,
+                    seed,
                 ),
                 c.point,
             )
@@ -60,29 +70,37 @@ pub fn with_data(radius: Precision, points: &SingleVec, clusters: &SingleVec) ->
     let mut points_outside = 0;
     let mut successes = 0;
     let mut circle_fails = 0;
+    let mut excluded = 0;
     let mut final_clusters = vec![];
 
     for (result, fallback) in centered.into_iter() {
-        match result {
+        let center = match result {
             sec::SmallestEnclosingCircle::RadiusTooBig => {
                 radius_too_big += 1;
-                final_clusters.push(fallback.center);
+                fallback.center
             }
             sec::SmallestEnclosingCircle::MissingPoints => {
                 points_outside += 1;
-                final_clusters.push(fallback.center);
+                fallback.center
             }
             sec::SmallestEnclosingCircle::Centered(center) => {
-                successes += 1;
-                final_clusters.push([center.y(), center.x()]);
+                if exclusions.contains(&[center.y(), center.x()]) {
+                    // the cluster it was centered from still covers its points
+                    excluded += 1;
+                    fallback.center
+                } else {
+                    successes += 1;
+                    [center.y(), center.x()]
+                }
             }
             sec::SmallestEnclosingCircle::None => {
                 circle_fails += 1;
-                final_clusters.push(fallback.center);
+                fallback.center
             }
-        }
+        };
+        final_clusters.extend(exclusions.relocate(center, radius));
     }
-    log::info!("Success: {successes} | Radius Too Big: {radius_too_big} | Missing Points: {points_outside} | Circle Fails: {circle_fails}");
+    log::info!("Success: {successes} | Radius Too Big: {radius_too_big} | Missing Points: {points_outside} | Circle Fails: {circle_fails} | Excluded: {excluded}");
     log::info!("centered clusters in {:.2}s", time.elapsed().as_secs_f32());
     final_clusters
 }
@@ -1,7 +1,7 @@
 use super::{circle::Circle, state::State, *};
 
 use geo::Point;
-use rand::seq::SliceRandom;
+use rand::{SeedableRng, rngs::SmallRng, seq::SliceRandom};
 
 #[derive(Debug)]
 pub enum SmallestEnclosingCircle {
@@ -15,11 +15,12 @@ pub fn multi_attempt<I: Iterator<Item = Point>>(
     points: I,
     radius: f64,
     max_attempts: usize,
+    seed: u64,
 ) -> SmallestEnclosingCircle {
     let points: Vec<_> = points.collect();
     let mut circle = Circle::None;
     let mut attempt = 0;
-    let mut rng = rand::rng();
+    let mut rng = SmallRng::seed_from_u64(seed);
 
     for i in 0..max_attempts {
         attempt = i;
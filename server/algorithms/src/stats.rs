@@ -1,14 +1,122 @@
 use std::{collections::HashMap, ops::AddAssign, time::Instant};
 
-use geo::{Distance, Haversine, Point};
+use geo::{Distance, Haversine, InterpolatePoint, Point};
 use hashbrown::HashSet;
-use model::api::{Precision, single_vec::SingleVec};
+use model::api::{Precision, point_array::PointArray, single_vec::SingleVec};
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use serde::Serialize;
 
-use crate::rtree::{self, cluster::Cluster, cluster_info, point};
+use crate::plugin::protocol::PluginReport;
+use crate::rtree::{self, Radii, Weights, cluster::Cluster, cluster_info, point};
 
 const WIDTH: &str = "=======================================================================";
+/// Hops that need more filler waypoints than this are only listed in the long hops
+const MAX_FILLERS_PER_HOP: usize = 1_000;
+/// Filler waypoints a route may get in total, the hops after that are only listed in the long hops
+const MAX_FILLERS_PER_ROUTE: usize = 10_000;
+
+/// The game's teleport cooldowns as `[meters, seconds]`
+const COOLDOWNS: [[Precision; 2]; 19] = [
+    [1_000., 60.],
+    [2_000., 60.],
+    [4_000., 120.],
+    [10_000., 480.],
+    [12_000., 540.],
+    [15_000., 660.],
+    [20_000., 780.],
+    [25_000., 900.],
+    [30_000., 1_080.],
+    [40_000., 1_320.],
+    [45_000., 1_380.],
+    [60_000., 1_500.],
+    [80_000., 1_620.],
+    [100_000., 1_800.],
+    [250_000., 2_700.],
+    [500_000., 3_600.],
+    [750_000., 4_500.],
+    [1_000_000., 5_400.],
+    [1_350_000., 7_200.],
+];
+
+/// Turns the distance of a hop into the seconds a device spends on it
+#[derive(Debug, Clone)]
+pub struct CycleModel {
+    /// `[meters, seconds]` sorted by distance and starting at `[0, 0]`
+    curve: Vec<[Precision; 2]>,
+    /// Meters per second the device moves at, `None` when it teleports
+    speed: Option<Precision>,
+    /// Whether the built-in sorter minimizes the cycle time instead of the distance
+    pub minimize: bool,
+}
+
+impl Default for CycleModel {
+    fn default() -> Self {
+        Self::new(vec![], None, false)
+    }
+}
+
+impl CycleModel {
+    /// Falls back to the game's cooldowns when the curve is empty
+    pub fn new(curve: Vec<[Precision; 2]>, speed: Option<Precision>, minimize: bool) -> Self {
+        let mut curve = if curve.is_empty() {
+            COOLDOWNS.to_vec()
+        } else {
+            curve
+        };
+        curve.retain(|[meters, seconds]| *meters > 0. && *seconds >= 0.);
+        curve.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        curve.insert(0, [0., 0.]);
+        Self {
+            curve,
+            speed: speed.filter(|speed| *speed > 0.),
+            minimize,
+        }
+    }
+
+    /// Cooldown linearly interpolated along the curve, the last point applies to anything further
+    pub fn cooldown(&self, meters: Precision) -> Precision {
+        let next = self
+            .curve
+            .partition_point(|[distance, _]| *distance < meters);
+        match (self.curve.get(next.wrapping_sub(1)), self.curve.get(next)) {
+            (Some([d1, s1]), Some([d2, s2])) => s1 + (s2 - s1) * (meters - d1) / (d2 - d1),
+            (Some([_, seconds]), None) | (None, Some([_, seconds])) => *seconds,
+            (None, None) => 0.,
+        }
+    }
+
+    /// The device waits out the cooldown, but can't arrive faster than its speed allows
+    pub fn hop_time(&self, meters: Precision) -> Precision {
+        let travel = self.speed.map_or(0., |speed| meters / speed);
+        self.cooldown(meters).max(travel)
+    }
+}
+
+/// Longest hop a route should take
+#[derive(Debug, Clone, Copy)]
+pub struct HopLimit {
+    /// In meters, the built-in sorter avoids longer hops
+    pub max_distance: Precision,
+    /// Whether hops that couldn't be avoided get filler waypoints instead of only being reported
+    pub fill: bool,
+}
+
+impl HopLimit {
+    /// No limit without a max distance
+    pub fn new(max_distance: Option<Precision>, fill: bool) -> Option<Self> {
+        max_distance.map(|max_distance| Self { max_distance, fill })
+    }
+}
+
+/// A hop above the [HopLimit]
+#[derive(Debug, Clone, Serialize)]
+pub struct LongHop {
+    pub from: PointArray,
+    pub to: PointArray,
+    pub distance: Precision,
+    /// Waypoints inserted to split the hop, 0 when it was left as is
+    pub fillers: usize,
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ClusterStats {
@@ -41,6 +149,14 @@ pub struct Stats {
     label: String,
     #[serde(skip_serializing)]
     min_points: usize,
+    #[serde(skip_serializing)]
+    weights: Weights,
+    #[serde(skip_serializing)]
+    radii: Radii,
+    #[serde(skip_serializing)]
+    cycle_model: CycleModel,
+    #[serde(skip_serializing)]
+    hop_limit: Option<HopLimit>,
 
     pub best_clusters: SingleVec,
     pub best_cluster_point_count: usize,
@@ -51,10 +167,52 @@ pub struct Stats {
     pub stats_time: Precision,
     pub total_points: usize,
     pub points_covered: usize,
+    pub total_weight: Precision,
+    pub weight_covered: Precision,
+    /// Number of data points per radius (in meters), only filled when per point radii were set
+    pub points_per_radius: HashMap<usize, usize>,
+    /// Number of data points per how many clusters they sit inside of, `0` being uncovered
+    pub points_per_coverage: HashMap<usize, usize>,
     pub total_clusters: usize,
     pub total_distance: Precision,
     pub longest_distance: Precision,
+    pub seed_distance: Precision,
+    pub distance_saved: Precision,
+    /// Meters that stitching the routes of neighboring cells together saved over simply joining them,
+    /// only set when a routing plugin ran per `route_split_level` cell
+    pub stitch_saved: Precision,
+    /// Circles the fixed hex lattice of a bootstrap needed, only set when its lattice was optimized
+    pub baseline_circles: usize,
+    /// Circles of the optimized hex lattice, never more than [Stats::baseline_circles]
+    pub optimized_circles: usize,
+    /// Whether the route is an open path, the hop from its last point back to the first is then left out
+    pub open_route: bool,
+    /// Seconds one loop of the route takes according to the [CycleModel]
+    pub total_cycle_time: Precision,
+    pub longest_hop_time: Precision,
+    /// Seconds of every hop in route order, the last one returns to the start unless the route is open
+    pub hop_times: Vec<Precision>,
+    /// Hops of the route above the [HopLimit]
+    pub long_hops: Vec<LongHop>,
+    /// Hops that were above the [HopLimit] and got filler waypoints
+    pub filled_hops: Vec<LongHop>,
     pub mygod_score: usize,
+    /// Proven lower bound on the mygod score over the candidate clusters, only set by the `exact` cluster mode
+    /// when no later step changed its clusters
+    pub lower_bound: Precision,
+    /// Relative gap between the mygod score and [Stats::lower_bound], 0 when the result is optimal
+    pub optimality_gap: Precision,
+    /// Seed that every randomized step of the request ran with, sending it back reproduces the result
+    pub seed: Option<u64>,
+    /// Distance of each device route, only set when the route was split between devices
+    pub device_distances: Vec<Precision>,
+    /// Cycle time of each device route, in seconds
+    pub device_cycle_times: Vec<Precision>,
+    /// How much longer the longest device route is than the average one, 0 when they are equal.
+    /// Compares cycle times when the [CycleModel] minimizes them, distances otherwise
+    pub device_imbalance: Precision,
+    /// Custom stats, point properties and errors of a plugin that speaks the JSON protocol
+    pub plugin: Option<PluginReport>,
     pub cluster_stats: ClusterStats,
 }
 
@@ -70,24 +228,57 @@ impl Stats {
             stats_time: 0.,
             total_points: 0,
             points_covered: 0,
+            total_weight: 0.,
+            weight_covered: 0.,
+            points_per_radius: HashMap::new(),
+            points_per_coverage: HashMap::new(),
             total_clusters: 0,
             total_distance: 0.,
             longest_distance: 0.,
+            seed_distance: 0.,
+            distance_saved: 0.,
+            stitch_saved: 0.,
+            baseline_circles: 0,
+            optimized_circles: 0,
+            open_route: false,
+            total_cycle_time: 0.,
+            longest_hop_time: 0.,
+            hop_times: vec![],
+            long_hops: vec![],
+            filled_hops: vec![],
             mygod_score: 0,
+            lower_bound: 0.,
+            optimality_gap: 0.,
+            seed: None,
+            device_distances: vec![],
+            device_cycle_times: vec![],
+            device_imbalance: 0.,
+            plugin: None,
             stats_start_time: None,
             label,
             min_points,
+            weights: Weights::default(),
+            radii: Radii::default(),
+            cycle_model: CycleModel::default(),
+            hop_limit: None,
             cluster_stats: ClusterStats::new(),
         }
     }
 
+    /// Uncovered points are counted by their weight, which is the plain point count when no weights were set
     pub fn get_score(&self) -> usize {
-        self.total_clusters * self.min_points + (self.total_points - self.points_covered)
+        self.total_clusters * self.min_points
+            + (self.total_weight - self.weight_covered).max(0.).round() as usize
     }
 
     pub fn set_score(&mut self) {
         self.start_timer();
         self.mygod_score = self.get_score();
+        if self.lower_bound > 0. && self.mygod_score > 0 {
+            self.optimality_gap = ((self.mygod_score as Precision - self.lower_bound)
+                / self.mygod_score as Precision)
+                .max(0.);
+        }
         self.stop_timer();
     }
 
@@ -107,7 +298,7 @@ impl Stats {
             )
         };
         log::info!(
-            "\n{}{}{}{}{}{}{}{}  {}==\n",
+            "\n{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}  {}==\n",
             get_row("[STATS] ".to_string(), false),
             if let Some(area) = area {
                 if area.is_empty() {
@@ -125,6 +316,55 @@ impl Stats {
                 ),
                 true
             ),
+            if self.weights.is_empty() {
+                "".to_string()
+            } else {
+                get_row(
+                    format!(
+                        "|| [WEIGHT] Total: {:.2} | Covered: {:.2}",
+                        self.total_weight, self.weight_covered,
+                    ),
+                    true,
+                )
+            },
+            if self.points_per_radius.is_empty() {
+                "".to_string()
+            } else {
+                let mut radii: Vec<_> = self.points_per_radius.iter().collect();
+                radii.sort();
+                get_row(
+                    format!(
+                        "|| [RADII] {}",
+                        radii
+                            .into_iter()
+                            .map(|(radius, count)| format!("{}m: {}", radius, count))
+                            .collect::<Vec<_>>()
+                            .join(" | "),
+                    ),
+                    true,
+                )
+            },
+            if self
+                .points_per_coverage
+                .keys()
+                .all(|coverage| *coverage <= 1)
+            {
+                "".to_string()
+            } else {
+                let mut coverage: Vec<_> = self.points_per_coverage.iter().collect();
+                coverage.sort();
+                get_row(
+                    format!(
+                        "|| [COVERAGE] {}",
+                        coverage
+                            .into_iter()
+                            .map(|(times, count)| format!("{}x: {}", times, count))
+                            .collect::<Vec<_>>()
+                            .join(" | "),
+                    ),
+                    true,
+                )
+            },
             get_row(
                 format!(
                     "|| [CLUSTERS] Total: {} | Avg Points: {}",
@@ -160,6 +400,82 @@ impl Stats {
                 ),
                 true
             ),
+            if self.hop_times.is_empty() {
+                "".to_string()
+            } else {
+                get_row(
+                    format!(
+                        "|| [CYCLE] Total: {}s | Longest Hop: {}s | Avg Hop: {}s",
+                        self.total_cycle_time as u32,
+                        self.longest_hop_time as u32,
+                        (self.total_cycle_time / self.hop_times.len() as Precision) as u32,
+                    ),
+                    true,
+                )
+            },
+            if let Some(hop_limit) = self.hop_limit {
+                get_row(
+                    format!(
+                        "|| [HOPS] Above {}m: {} | Filled: {}",
+                        hop_limit.max_distance as u32,
+                        self.long_hops.len(),
+                        self.filled_hops.len(),
+                    ),
+                    true,
+                )
+            } else {
+                "".to_string()
+            },
+            if self.device_distances.len() > 1 {
+                get_row(
+                    format!(
+                        "|| [DEVICES] {} | Longest: {}m | Shortest: {}m | Imbalance: {:.2}%",
+                        self.device_distances.len(),
+                        self.device_distances
+                            .iter()
+                            .copied()
+                            .fold(0., Precision::max) as u32,
+                        self.device_distances
+                            .iter()
+                            .copied()
+                            .fold(Precision::MAX, Precision::min) as u32,
+                        self.device_imbalance * 100.,
+                    ),
+                    true,
+                )
+            } else {
+                "".to_string()
+            },
+            if self.seed_distance > 0. {
+                get_row(
+                    format!(
+                        "|| [OPTIMIZATION] Seed: {}m | Saved: {}m",
+                        self.seed_distance as u32, self.distance_saved as u32,
+                    ),
+                    true,
+                )
+            } else {
+                "".to_string()
+            },
+            if self.stitch_saved > 0. {
+                get_row(
+                    format!("|| [STITCH] Saved: {}m", self.stitch_saved as u32),
+                    true,
+                )
+            } else {
+                "".to_string()
+            },
+            if self.baseline_circles > 0 {
+                get_row(
+                    format!(
+                        "|| [LATTICE] Baseline: {} | Optimized: {}",
+                        self.baseline_circles, self.optimized_circles,
+                    ),
+                    true,
+                )
+            } else {
+                "".to_string()
+            },
             get_row(
                 format!(
                     "|| [TIMES] Clustering: {:.2}s | Routing: {:.2}s | Stats: {:.2}s",
@@ -168,6 +484,23 @@ impl Stats {
                 true
             ),
             get_row(format!("|| [MYGOD_SCORE] {}", self.mygod_score,), true),
+            if self.lower_bound > 0. {
+                get_row(
+                    format!(
+                        "|| [OPTIMALITY] Lower Bound: {:.0} | Gap: {:.2}%",
+                        self.lower_bound,
+                        self.optimality_gap * 100.,
+                    ),
+                    true,
+                )
+            } else {
+                "".to_string()
+            },
+            if let Some(seed) = self.seed {
+                get_row(format!("|| [SEED] {}", seed), true)
+            } else {
+                "".to_string()
+            },
             WIDTH,
         )
     }
@@ -175,11 +508,70 @@ impl Stats {
     pub fn distance_stats(&mut self, clusters: &SingleVec) {
         self.start_timer();
         log::info!("generating distance stats for {} points", clusters.len());
+        self.reset_distances();
+        self.add_distances(clusters);
+        if self.seed_distance > 0. {
+            self.distance_saved = self.seed_distance - self.total_distance;
+        }
+        log::info!(
+            "distance stats complete {:.4}s",
+            self.stats_start_time.unwrap().elapsed().as_secs_f32()
+        );
+        self.stop_timer();
+    }
+
+    /// Distance stats over the routes of every device, the total being their sum
+    pub fn device_stats(&mut self, routes: &[SingleVec]) {
+        self.start_timer();
+        self.reset_distances();
+        (self.device_distances, self.device_cycle_times) = routes
+            .iter()
+            .map(|route| {
+                let (distance, cycle_time) = (self.total_distance, self.total_cycle_time);
+                self.add_distances(route);
+                (
+                    self.total_distance - distance,
+                    self.total_cycle_time - cycle_time,
+                )
+            })
+            .unzip();
+        self.set_device_imbalance();
+        if self.seed_distance > 0. {
+            self.distance_saved = self.seed_distance - self.total_distance;
+        }
+        self.stop_timer();
+    }
+
+    fn set_device_imbalance(&mut self) {
+        let lengths = if self.cycle_model.minimize {
+            &self.device_cycle_times
+        } else {
+            &self.device_distances
+        };
+        let average = lengths.iter().sum::<Precision>() / lengths.len().max(1) as Precision;
+        self.device_imbalance = if average > 0. {
+            lengths.iter().copied().fold(0., Precision::max) / average - 1.
+        } else {
+            0.
+        };
+    }
+
+    fn reset_distances(&mut self) {
         self.total_distance = 0.;
         self.longest_distance = 0.;
+        self.total_cycle_time = 0.;
+        self.longest_hop_time = 0.;
+        self.hop_times.clear();
+        self.long_hops.clear();
+    }
+
+    fn add_distances(&mut self, clusters: &SingleVec) {
         for (i, point) in clusters.iter().enumerate() {
             let point = Point::new(point[1], point[0]);
             let point2 = if i == clusters.len() - 1 {
+                if self.open_route {
+                    break;
+                }
                 Point::new(clusters[0][1], clusters[0][0])
             } else {
                 Point::new(clusters[i + 1][1], clusters[i + 1][0])
@@ -189,12 +581,156 @@ impl Stats {
             if distance > self.longest_distance {
                 self.longest_distance = distance;
             }
+            let hop_time = self.cycle_model.hop_time(distance);
+            self.total_cycle_time += hop_time;
+            self.longest_hop_time = self.longest_hop_time.max(hop_time);
+            self.hop_times.push(hop_time);
+            if let Some(hop_limit) = self.hop_limit
+                && distance > hop_limit.max_distance
+            {
+                self.long_hops.push(LongHop {
+                    from: [point.y(), point.x()],
+                    to: [point2.y(), point2.x()],
+                    distance,
+                    fillers: 0,
+                });
+            }
         }
-        log::info!(
-            "distance stats complete {:.4}s",
-            self.stats_start_time.unwrap().elapsed().as_secs_f32()
+    }
+
+    /// Splits every hop above the [HopLimit] with evenly spaced waypoints if it asks for it, call before the distance stats.
+    /// Hops above [MAX_FILLERS_PER_HOP] or past [MAX_FILLERS_PER_ROUTE] are left as they are and listed as long hops
+    pub fn fill_long_hops(&mut self, route: SingleVec) -> SingleVec {
+        let Some(hop_limit) = self.hop_limit.filter(|hop_limit| hop_limit.fill) else {
+            return route;
+        };
+        let mut filled = Vec::with_capacity(route.len());
+        let mut total = 0;
+        let mut skipped = 0;
+        for (i, from) in route.iter().enumerate() {
+            filled.push(*from);
+            let to = match route.get(i + 1) {
+                Some(to) => to,
+                None if self.open_route => break,
+                None => &route[0],
+            };
+            let (start, end) = (Point::new(from[1], from[0]), Point::new(to[1], to[0]));
+            let distance = Haversine.distance(start, end);
+            if distance <= hop_limit.max_distance {
+                continue;
+            }
+            let fillers = (distance / hop_limit.max_distance).floor() as usize;
+            if fillers > MAX_FILLERS_PER_HOP || total + fillers > MAX_FILLERS_PER_ROUTE {
+                skipped += 1;
+                continue;
+            }
+            total += fillers;
+            for step in 1..=fillers {
+                let point = Haversine.point_at_ratio_between(
+                    start,
+                    end,
+                    step as Precision / (fillers + 1) as Precision,
+                );
+                filled.push([point.y(), point.x()]);
+            }
+            self.filled_hops.push(LongHop {
+                from: *from,
+                to: *to,
+                distance,
+                fillers,
+            });
+        }
+        if !self.filled_hops.is_empty() {
+            log::info!(
+                "filled {} hops above {}m with {} waypoints",
+                self.filled_hops.len(),
+                hop_limit.max_distance,
+                filled.len() - route.len()
+            );
+        }
+        if skipped > 0 {
+            log::warn!("{skipped} hops needed too many waypoints and are only listed as long hops");
+        }
+        filled
+    }
+
+    /// Weights the data points passed to [Stats::cluster_stats], pairs with `points` by index
+    pub fn set_weights(&mut self, points: &SingleVec, weights: &[Precision]) {
+        self.weights = rtree::Weights::new(points, weights);
+    }
+
+    /// Gives the data points passed to [Stats::cluster_stats] their own radius, pairs with `points` by index
+    pub fn set_radii(&mut self, points: &SingleVec, radii: &[Precision]) {
+        self.radii = rtree::Radii::new(points, radii);
+    }
+
+    /// How hops are timed in the distance stats and, if [CycleModel::minimize] is set, what the built-in sorter optimizes for
+    pub fn set_cycle_model(&mut self, cycle_model: CycleModel) {
+        self.cycle_model = cycle_model;
+    }
+
+    pub fn cycle_model(&self) -> &CycleModel {
+        &self.cycle_model
+    }
+
+    /// Which hops are too long for the distance stats and the built-in sorter
+    pub fn set_hop_limit(&mut self, hop_limit: Option<HopLimit>) {
+        self.hop_limit = hop_limit;
+    }
+
+    pub fn hop_limit(&self) -> Option<HopLimit> {
+        self.hop_limit
+    }
+
+    /// Leaves the hop from the last point back to the first out of the distance stats
+    pub fn set_open_route(&mut self, open_route: bool) {
+        self.open_route = open_route;
+    }
+
+    pub fn set_lower_bound(&mut self, lower_bound: Precision) {
+        self.lower_bound = lower_bound;
+        log::debug!("Lower Bound: {}", self.lower_bound);
+    }
+
+    /// Uses the requested seed or draws a new one and returns it
+    pub fn set_seed(&mut self, seed: Option<u64>) -> u64 {
+        // kept within 53 bits so that JavaScript clients can send it back unchanged
+        let seed = seed.unwrap_or_else(|| rand::random::<u64>() >> 11);
+        self.seed = Some(seed);
+        log::debug!("Seed: {}", seed);
+        seed
+    }
+
+    pub fn set_stitch_saved(&mut self, distance: Precision) {
+        self.stitch_saved = distance;
+        log::debug!("Stitch Saved: {}m", self.stitch_saved as Precision);
+    }
+
+    pub fn set_lattice_circles(&mut self, baseline: usize, optimized: usize) {
+        self.baseline_circles = baseline;
+        self.optimized_circles = optimized;
+        log::debug!("Lattice Circles: {} -> {}", baseline, optimized);
+    }
+
+    /// Merges the report into the one of an earlier plugin run
+    pub fn add_plugin_report(&mut self, report: Option<PluginReport>) {
+        let Some(report) = report else {
+            return;
+        };
+        log::debug!(
+            "Plugin Report: {} stats, {} properties, {} errors",
+            report.stats.len(),
+            report.properties.len(),
+            report.errors.len()
         );
-        self.stop_timer();
+        self.plugin
+            .get_or_insert_with(PluginReport::default)
+            .merge(&report);
+    }
+
+    pub fn set_seed_distance(&mut self, distance: Precision) {
+        self.seed_distance = distance;
+        log::debug!("Seed Distance: {}m", self.seed_distance as Precision);
     }
 
     pub fn set_cluster_time(&mut self, time: Instant) {
@@ -227,15 +763,18 @@ impl Stats {
 
         if points.is_empty() {
         } else {
-            let tree = rtree::spawn(radius, points);
+            let tree = rtree::spawn_with(radius, points, &self.weights, &self.radii);
+            let cluster_radius = self.radii.max(radius);
             let clusters: Vec<point::Point> = clusters
                 .into_iter()
-                .map(|c| point::Point::new(radius, 20, *c))
+                .map(|c| point::Point::new(cluster_radius, 20, *c))
                 .collect();
             let mut clusters: Vec<Cluster<'_>> = cluster_info(&tree, &clusters);
 
-            let cluster_tree =
-                rtree::spawn(radius, &clusters.iter().map(|c| c.point.center).collect());
+            let cluster_tree = rtree::spawn(
+                cluster_radius,
+                &clusters.iter().map(|c| c.point.center).collect(),
+            );
 
             clusters
                 .par_iter_mut()
@@ -279,6 +818,40 @@ impl Stats {
             self.worst_cluster_count = worst_count;
             self.best_clusters = best_clusters;
             self.points_covered = points_covered.len();
+            self.total_weight = tree.iter().map(|p| p.weight).sum();
+            self.weight_covered = points_covered.iter().map(|p| p.weight).sum();
+            let mut coverage: HashMap<[u64; 2], usize> = HashMap::new();
+            for cluster in clusters.iter() {
+                // duplicated data points share their coordinates, a cluster covers them once
+                let keys: HashSet<[u64; 2]> = cluster
+                    .all
+                    .iter()
+                    .map(|point| point.center.map(Precision::to_bits))
+                    .collect();
+                for key in keys {
+                    *coverage.entry(key).or_insert(0) += 1;
+                }
+            }
+            self.points_per_coverage.clear();
+            for point in tree.iter() {
+                let times = coverage
+                    .get(&point.center.map(Precision::to_bits))
+                    .copied()
+                    .unwrap_or(0);
+                self.points_per_coverage
+                    .entry(times)
+                    .and_modify(|count| *count += 1)
+                    .or_insert(1);
+            }
+            if !self.radii.is_empty() {
+                self.points_per_radius.clear();
+                for point in tree.iter() {
+                    self.points_per_radius
+                        .entry(point.radius.round() as usize)
+                        .and_modify(|count| *count += 1)
+                        .or_insert(1);
+                }
+            }
 
             if self.points_covered > self.total_points {
                 log::warn!(
@@ -309,9 +882,36 @@ impl<'a> AddAssign<&'a Self> for Stats {
         self.stats_time += rhs.stats_time;
         self.total_points += rhs.total_points;
         self.points_covered += rhs.points_covered;
+        self.total_weight += rhs.total_weight;
+        self.weight_covered += rhs.weight_covered;
         self.total_clusters += rhs.total_clusters;
         self.total_distance += rhs.total_distance;
         self.longest_distance += rhs.longest_distance;
+        self.seed_distance += rhs.seed_distance;
+        self.distance_saved += rhs.distance_saved;
+        self.stitch_saved += rhs.stitch_saved;
+        self.baseline_circles += rhs.baseline_circles;
+        self.optimized_circles += rhs.optimized_circles;
+        self.open_route |= rhs.open_route;
+        self.total_cycle_time += rhs.total_cycle_time;
+        self.longest_hop_time = self.longest_hop_time.max(rhs.longest_hop_time);
+        self.hop_times.extend(rhs.hop_times.iter().copied());
+        self.long_hops.extend(rhs.long_hops.iter().cloned());
+        self.filled_hops.extend(rhs.filled_hops.iter().cloned());
+        self.lower_bound += rhs.lower_bound;
+        self.seed = self.seed.or(rhs.seed);
+        if let Some(report) = rhs.plugin.as_ref() {
+            self.plugin
+                .get_or_insert_with(PluginReport::default)
+                .merge(report);
+        }
+        if !rhs.device_distances.is_empty() {
+            self.device_distances
+                .extend(rhs.device_distances.iter().copied());
+            self.device_cycle_times
+                .extend(rhs.device_cycle_times.iter().copied());
+            self.set_device_imbalance();
+        }
 
         macro_rules! merge_stat_field {
             ($dst:expr, $src:expr, $field:ident) => {{
@@ -323,6 +923,8 @@ impl<'a> AddAssign<&'a Self> for Stats {
 
         merge_stat_field!(self.cluster_stats, rhs.cluster_stats, all);
         merge_stat_field!(self.cluster_stats, rhs.cluster_stats, unique);
+        merge_stat_field!(self, rhs, points_per_radius);
+        merge_stat_field!(self, rhs, points_per_coverage);
 
         self.set_score();
     }
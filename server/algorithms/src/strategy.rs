@@ -0,0 +1,196 @@
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+
+use geojson::{Feature, FeatureCollection};
+use model::api::{
+    Precision, args::RouteStart, calc_mode::CalculationMode, cluster_mode::ClusterMode,
+    single_vec::SingleVec, sort_by::SortBy,
+};
+
+use crate::{
+    bootstrap, clustering, exclusion::Exclusions, progress::Progress, routing, stats::Stats,
+};
+
+pub use crate::rtree::{Radii, Weights};
+
+/// Clustering strategies by name, `ClusterMode` values, the custom names of requests and `s2` are looked up here
+/// before the clustering plugins
+pub static CLUSTERING: LazyLock<Registry<dyn ClusterStrategy>> =
+    LazyLock::new(|| Registry::new(clustering::strategies::builtins()));
+/// Routing strategies by name, `SortBy` values and the custom names of requests are looked up here
+/// before the routing plugins
+pub static ROUTING: LazyLock<Registry<dyn RouteStrategy>> =
+    LazyLock::new(|| Registry::new(routing::strategies::builtins()));
+/// Bootstrap strategies by name, `CalculationMode` values and the custom names of requests are looked up here
+/// before the bootstrap plugins
+pub static BOOTSTRAP: LazyLock<Registry<dyn BootstrapStrategy>> =
+    LazyLock::new(|| Registry::new(bootstrap::strategies::builtins()));
+
+/// Everything [clustering::main] needs besides the stats, the [ClusterStrategy] it picks gets the same input
+pub struct ClusterInput<'a> {
+    pub data_points: &'a SingleVec,
+    /// Weight of every data point, paired by index, empty when they all weigh the same
+    pub weights: &'a [Precision],
+    /// Radius of every data point, paired by index, empty when they all use `radius`
+    pub radii: &'a [Precision],
+    /// Radius of the points without one in `radii`
+    pub radius: Precision,
+    pub min_points: usize,
+    pub max_clusters: usize,
+    pub cluster_mode: ClusterMode,
+    pub cluster_split_level: u64,
+    /// [CalculationMode::S2] uses the strategy registered as `s2` instead of the one of `cluster_mode`
+    pub calculation_mode: CalculationMode,
+    pub s2_level: u8,
+    pub s2_size: u8,
+    pub area: &'a FeatureCollection,
+    pub args: &'a str,
+    pub center_clusters: bool,
+    pub genetic_post_processing: bool,
+    pub genetic_time_budget: Duration,
+    /// Larger inputs are clustered with [ClusterMode::Best] instead of [ClusterMode::Exact]
+    pub exact_max_points: usize,
+    /// How long [ClusterMode::Exact] searches for before it keeps the best clusters found so far
+    pub exact_time_budget: Duration,
+    pub min_coverage: usize,
+    pub seed: u64,
+    /// Areas no cluster may have its center in
+    pub exclusions: &'a Exclusions,
+    pub progress: &'a Progress,
+}
+
+impl ClusterInput<'_> {
+    pub fn point_weights(&self) -> Weights {
+        Weights::new(self.data_points, self.weights)
+    }
+
+    pub fn point_radii(&self) -> Radii {
+        Radii::new(self.data_points, self.radii)
+    }
+}
+
+pub trait ClusterStrategy: Send + Sync {
+    /// Clusters covering the data points, centering, post processing and the stats of the result
+    /// are left to [clustering::main]
+    fn cluster(&self, input: &ClusterInput, stats: &mut Stats) -> SingleVec;
+}
+
+/// Everything [routing::main] passes to a [RouteStrategy] besides the clusters and the stats
+pub struct RouteInput<'a> {
+    pub data_points: &'a SingleVec,
+    pub radius: Precision,
+    pub route_split_level: u64,
+    pub args: &'a str,
+    pub seed: u64,
+    pub progress: &'a Progress,
+}
+
+pub trait RouteStrategy: Send + Sync {
+    /// The clusters in the order of the closed route, rotating it to its start is left to [routing::main]
+    fn route(&self, clusters: SingleVec, input: &RouteInput, stats: &mut Stats) -> SingleVec;
+}
+
+/// Everything [bootstrap::main] needs besides the area and the stats, the [BootstrapStrategy] it picks
+/// gets the same input for every feature of the area
+pub struct BootstrapInput<'a> {
+    /// Picks the strategy, custom values name a registered strategy or a plugin
+    pub calculation_mode: CalculationMode,
+    pub radius: Precision,
+    pub s2_level: u8,
+    pub s2_size: u8,
+    pub sort_by: &'a SortBy,
+    pub route_split_level: u64,
+    pub routing_args: &'a str,
+    pub args: &'a str,
+    /// Whether the hex lattice of [bootstrap::radius::BootstrapRadius] is optimized and for how long
+    pub optimize_lattice: bool,
+    pub lattice_time_budget: Duration,
+    /// Areas no circle may have its center in
+    pub exclusions: &'a Exclusions,
+    pub seed: u64,
+    pub route_start: &'a RouteStart,
+    pub progress: &'a Progress,
+}
+
+pub trait BootstrapStrategy: Send + Sync {
+    /// Points covering one feature of the area, already routed, `None` when it failed, which is logged
+    fn bootstrap(
+        &self,
+        feature: &Feature,
+        input: &BootstrapInput,
+        stats: &mut Stats,
+    ) -> Option<Feature>;
+}
+
+struct Entry<T: ?Sized> {
+    name: String,
+    builtin: bool,
+    strategy: Arc<T>,
+}
+
+/// Strategies by their lowercase name, in the order they were registered
+pub struct Registry<T: ?Sized>(RwLock<Vec<Entry<T>>>);
+
+impl<T: ?Sized> Registry<T> {
+    fn new(builtins: Vec<(&str, Arc<T>)>) -> Self {
+        Self(RwLock::new(
+            builtins
+                .into_iter()
+                .map(|(name, strategy)| Entry {
+                    name: name.to_lowercase(),
+                    builtin: true,
+                    strategy,
+                })
+                .collect(),
+        ))
+    }
+
+    /// Adds the strategy, or replaces the one with the same name, including the built-in ones.
+    /// It takes precedence over a plugin with the same name
+    pub fn register(&self, name: &str, strategy: Arc<T>) {
+        let name = name.to_lowercase();
+        let Ok(mut entries) = self.0.write() else {
+            return;
+        };
+        match entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => entry.strategy = strategy,
+            None => entries.push(Entry {
+                name,
+                builtin: false,
+                strategy,
+            }),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<T>> {
+        let name = name.to_lowercase();
+        let entries = self.0.read().ok()?;
+        entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.strategy.clone())
+    }
+
+    /// Names of the strategies that ship with Kōji
+    pub fn builtin_names(&self) -> Vec<String> {
+        self.names(true)
+    }
+
+    /// Names of the strategies registered by other crates
+    pub fn custom_names(&self) -> Vec<String> {
+        self.names(false)
+    }
+
+    fn names(&self, builtin: bool) -> Vec<String> {
+        self.0
+            .read()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| entry.builtin == builtin)
+                    .map(|entry| entry.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
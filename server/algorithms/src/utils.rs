@@ -1,12 +1,10 @@
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
-use std::fs;
 use std::fs::{File, create_dir_all};
 use std::io::Write;
-use std::path::Path;
 
 use colored::Colorize;
-use geo::Coord;
+use geo::{Coord, Distance, Haversine, Point};
 use geohash::encode;
 use hashbrown::HashSet;
 use model::api::Precision;
@@ -144,28 +142,23 @@ pub fn rotate_to_best(clusters: SingleVec, stats: &Stats) -> SingleVec {
     final_clusters.into()
 }
 
-pub fn get_plugin_list(path: &str) -> std::io::Result<Vec<String>> {
-    let path = Path::new(path);
-
-    fs::read_dir(path)?
-        .map(|res| res.map(|e| e.path().display().to_string()))
-        .filter_map(|path| {
-            if let Ok(ext) = path {
-                let plugin = ext
-                    .split(std::path::MAIN_SEPARATOR_STR)
-                    .last()
-                    .unwrap_or("")
-                    .to_string();
-                if plugin == ".gitkeep" {
-                    None
-                } else {
-                    Some(Ok(plugin))
-                }
-            } else {
-                None
-            }
-        })
-        .collect::<Result<Vec<_>, std::io::Error>>()
+/// Index of the cluster closest to `point`, 0 when there are none
+pub fn closest(clusters: &SingleVec, point: PointArray) -> usize {
+    let point = Point::new(point[1], point[0]);
+    clusters
+        .iter()
+        .map(|[lat, lon]| Haversine.distance(point, Point::new(*lon, *lat)))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Rotates the route so it starts at the cluster closest to `start`
+pub fn rotate_to_point(mut clusters: SingleVec, start: PointArray) -> SingleVec {
+    let closest = closest(&clusters, start);
+    clusters.rotate_left(closest);
+    clusters
 }
 
 pub fn stringify_points(points: &SingleVec) -> String {
@@ -40,6 +40,9 @@ pub async fn start() -> io::Result<()> {
         .to_string()
     };
 
+    // shared between the workers so progress can be read from any of them
+    let jobs = web::Data::new(utils::jobs::Jobs::default());
+
     HttpServer::new(move || {
         let client = nominatim::Client::new(
             url::Url::parse(
@@ -56,6 +59,7 @@ pub async fn start() -> io::Result<()> {
         App::new()
             .app_data(web::Data::new(databases.clone()))
             .app_data(web::Data::new(client))
+            .app_data(jobs.clone())
             // increase max payload size to 50MB
             .app_data(web::JsonConfig::default().limit(1024 * 1024 * 50))
             .wrap(middleware::Logger::new("%s | %r - %b bytes in %D ms (%a)"))
@@ -125,8 +129,13 @@ pub async fn start() -> io::Result<()> {
                                 .service(public::v1::calculate::bootstrap)
                                 .service(public::v1::calculate::route_stats)
                                 .service(public::v1::calculate::route_stats_category)
+                                .service(public::v1::calculate::route_diff)
+                                .service(public::v1::calculate::route_diff_category)
                                 .service(public::v1::calculate::reroute)
                                 .service(public::v1::calculate::calculate_area)
+                                .service(public::v1::calculate::recluster)
+                                .service(public::v1::calculate::job_progress)
+                                .service(public::v1::calculate::cancel_job)
                                 .service(public::v1::calculate::cluster),
                         )
                         .service(
@@ -26,9 +26,9 @@ async fn config(conn: web::Data<KojiDb>, session: Session) -> Result<HttpRespons
         .unwrap();
 
     let tile_server = std::env::var("TILE_SERVER").unwrap_or("".to_string());
-    let route_plugins = routing::routing_plugins();
-    let clustering_plugins = clustering::clustering_plugins();
-    let bootstrap_plugins = bootstrap::bootstrap_plugins();
+    let route_plugins = routing::custom_routing_options();
+    let clustering_plugins = clustering::custom_clustering_options();
+    let bootstrap_plugins = bootstrap::custom_bootstrap_options();
 
     Ok(HttpResponse::Ok().json(ConfigResponse {
         start_lat,
@@ -44,6 +44,9 @@ async fn config(conn: web::Data<KojiDb>, session: Session) -> Result<HttpRespons
         route_plugins,
         clustering_plugins,
         bootstrap_plugins,
+        route_plugin_manifests: routing::routing_plugin_manifests(),
+        clustering_plugin_manifests: clustering::clustering_plugin_manifests(),
+        bootstrap_plugin_manifests: bootstrap::bootstrap_plugin_manifests(),
     }))
 }
 
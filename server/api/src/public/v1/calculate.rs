@@ -1,8 +1,20 @@
-use crate::utils::{request, response::Response};
+use std::time::Duration;
+
+use crate::utils::{
+    jobs::{Job, Jobs},
+    request,
+    response::Response,
+};
 
 use super::*;
 
-use algorithms::{self, clustering, routing, stats::Stats};
+use actix_web::http::header::{self, ContentEncoding};
+use algorithms::{
+    self, clustering, routing,
+    stats::{CycleModel, HopLimit, Stats},
+    strategy::{BootstrapInput, ClusterInput},
+};
+use futures::StreamExt;
 use geo::{ChamberlainDuquetteArea, MultiPolygon, Polygon};
 
 use geojson::Value;
@@ -10,16 +22,23 @@ use model::{
     KojiDb, ScannerType,
     api::{
         FeatureHelpers, GeoFormats, ToCollection, ToFeature, ToSingleVec,
-        args::{Args, ArgsUnwrapped},
+        args::{Args, ArgsUnwrapped, RouteRef},
         sort_by::SortBy,
     },
-    db::{area, geofence, instance, route, sea_orm_active_enums::Type},
+    db::{GetRadii, GetWeights, area, geofence, instance, route, sea_orm_active_enums::Type},
+    error::ModelError,
 };
 use serde_json::json;
 
+/// How often the progress of a job is sent to the event stream
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Intervals the event stream waits for a job that hasn't been started yet
+const PROGRESS_WAIT: usize = 40;
+
 #[post("/bootstrap")]
 async fn bootstrap(
     conn: web::Data<KojiDb>,
+    jobs: web::Data<Jobs>,
     payload: web::Json<Args>,
 ) -> Result<HttpResponse, Error> {
     let ArgsUnwrapped {
@@ -39,6 +58,13 @@ async fn bootstrap(
         route_split_level,
         routing_args,
         bootstrapping_args,
+        optimize_lattice,
+        lattice_time_budget,
+        exclusions,
+        exclusion_fences,
+        seed,
+        job_id,
+        route_start,
         ..
     } = payload.into_inner().init(Some("bootstrap"));
 
@@ -51,21 +77,40 @@ async fn bootstrap(
     let area = utils::create_or_find_collection(&instance, &conn, area, &parent, &vec![])
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
+    let exclusions = match utils::load_exclusions(exclusions, &exclusion_fences, &conn).await {
+        Ok(exclusions) => exclusions,
+        Err(response) => return Ok(response),
+    };
 
     let mut stats = Stats::new(format!("Bootstrap | {:?}", calculation_mode), 1);
+    let seed = stats.set_seed(seed);
 
-    let mut features: Vec<Feature> = algorithms::bootstrap::main(
-        area,
-        calculation_mode,
-        radius,
-        sort_by,
-        s2_level,
-        s2_size,
-        route_split_level,
-        &mut stats,
-        &routing_args,
-        &bootstrapping_args,
-    );
+    let job = Jobs::start(&jobs, job_id);
+    let progress = job.progress.clone();
+    let (mut features, stats) = web::block(move || {
+        let input = BootstrapInput {
+            calculation_mode,
+            radius,
+            s2_level,
+            s2_size,
+            sort_by: &sort_by,
+            route_split_level,
+            routing_args: &routing_args,
+            args: &bootstrapping_args,
+            optimize_lattice,
+            lattice_time_budget: Duration::from_secs(lattice_time_budget),
+            exclusions: &exclusions,
+            seed,
+            route_start: &route_start,
+            progress: &progress,
+        };
+        let features: Vec<Feature> = algorithms::bootstrap::main(area, &input, &mut stats);
+        (features, stats)
+    })
+    .await?;
+    if let Some(response) = cancelled(job) {
+        return Ok(response);
+    }
 
     if parent.is_some() {
         let mut condensed = vec![];
@@ -148,6 +193,7 @@ async fn bootstrap(
 #[post("/{mode}/{category}")]
 async fn cluster(
     conn: web::Data<KojiDb>,
+    jobs: web::Data<Jobs>,
     url: actix_web::web::Path<(String, String)>,
     payload: web::Json<Args>,
 ) -> Result<HttpResponse, Error> {
@@ -159,9 +205,12 @@ async fn cluster(
         cluster_mode,
         cluster_split_level,
         data_points,
+        data_point_weights,
+        data_point_radii,
         instance,
         min_points,
         radius,
+        radii,
         return_type,
         save_to_db,
         save_to_scanner,
@@ -179,6 +228,22 @@ async fn cluster(
         clustering_args,
         center_clusters,
         genetic_post_processing,
+        genetic_time_budget,
+        seed,
+        weighted,
+        exact_max_points,
+        exact_time_budget,
+        min_coverage,
+        devices,
+        job_id,
+        cooldown_curve,
+        speed,
+        minimize_cycle_time,
+        max_hop_distance,
+        fill_long_hops,
+        exclusions,
+        exclusion_fences,
+        route_start,
         ..
     } = payload.into_inner().init(Some(&mode));
 
@@ -198,6 +263,9 @@ async fn cluster(
         format!("{:?} | {:?}", cluster_mode, calculation_mode),
         min_points,
     );
+    stats.set_cycle_model(CycleModel::new(cooldown_curve, speed, minimize_cycle_time));
+    stats.set_hop_limit(HopLimit::new(max_hop_distance, fill_long_hops));
+    let seed = stats.set_seed(seed);
     let enum_type = if category == "gym" || category == "fort" {
         if conn.scanner_type == ScannerType::Unown {
             Type::CircleRaid
@@ -220,13 +288,27 @@ async fn cluster(
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let data_points = if data_points.is_empty() {
-        utils::points_from_area(&area, &category, &conn, last_seen, tth)
+    let exclusions = match utils::load_exclusions(exclusions, &exclusion_fences, &conn).await {
+        Ok(exclusions) => exclusions,
+        Err(response) => return Ok(response),
+    };
+    let (data_points, data_point_weights, data_point_radii) = if data_points.is_empty() {
+        let points = utils::points_from_area(&area, &category, &conn, last_seen, tth)
             .await
-            .map_err(actix_web::error::ErrorInternalServerError)?
-            .to_single_vec()
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let weights = if weighted {
+            points.get_weights()
+        } else {
+            vec![]
+        };
+        let point_radii = if radii.is_empty() {
+            vec![]
+        } else {
+            points.get_radii(&radii, radius)
+        };
+        (points.to_single_vec(), weights, point_radii)
     } else {
-        data_points
+        (data_points, data_point_weights, data_point_radii)
     };
 
     log::debug!(
@@ -235,35 +317,56 @@ async fn cluster(
         data_points.len()
     );
 
-    let clusters = clustering::main(
-        &data_points,
-        cluster_mode,
-        radius,
-        min_points,
-        &mut stats,
-        cluster_split_level,
-        max_clusters,
-        calculation_mode,
-        s2_level,
-        s2_size,
-        area,
-        &clustering_args,
-        center_clusters,
-        genetic_post_processing,
-    );
-    let clusters = routing::main(
-        &data_points,
-        clusters,
-        &sort_by,
-        route_split_level,
-        radius,
-        &mut stats,
-        &routing_args,
-    );
-
-    let mut feature = clusters
-        .to_feature(Some(enum_type.clone()))
-        .remove_last_coord();
+    let job = Jobs::start(&jobs, job_id);
+    let progress = job.progress.clone();
+    let (routes, stats) = web::block(move || {
+        let input = ClusterInput {
+            data_points: &data_points,
+            weights: &data_point_weights,
+            radii: &data_point_radii,
+            radius,
+            min_points,
+            max_clusters,
+            cluster_mode,
+            cluster_split_level,
+            calculation_mode,
+            s2_level,
+            s2_size,
+            area: &area,
+            args: &clustering_args,
+            center_clusters,
+            genetic_post_processing,
+            genetic_time_budget: Duration::from_secs(genetic_time_budget),
+            exact_max_points,
+            exact_time_budget: Duration::from_secs(exact_time_budget),
+            min_coverage,
+            seed,
+            exclusions: &exclusions,
+            progress: &progress,
+        };
+        let clusters = clustering::main(&input, &mut stats);
+        let routes = if devices > 1 {
+            routing::devices::main(&clusters, devices, &mut stats, &route_start, &progress)
+        } else {
+            vec![routing::main(
+                &data_points,
+                clusters,
+                &sort_by,
+                route_split_level,
+                radius,
+                &mut stats,
+                &routing_args,
+                seed,
+                &route_start,
+                &progress,
+            )]
+        };
+        (routes, stats)
+    })
+    .await?;
+    if let Some(response) = cancelled(job) {
+        return Ok(response);
+    }
 
     let instance = if let Some(parent) = parent {
         let model = geofence::Query::get_one(&conn.koji, parent.to_string())
@@ -273,8 +376,30 @@ async fn cluster(
     } else {
         instance
     };
-    feature.add_instance_properties(Some(instance.to_string()), Some(enum_type));
-    let feature = feature.to_collection(Some(instance.clone()), None);
+    let mut applied_radii: Vec<_> = stats.points_per_radius.keys().copied().collect();
+    applied_radii.sort();
+    let device_count = routes.len();
+    let open_route = stats.open_route;
+    let feature = routes
+        .into_iter()
+        .enumerate()
+        .map(|(index, clusters)| {
+            let mut feature = clusters
+                .to_feature(Some(enum_type.clone()))
+                .remove_last_coord();
+            feature.set_property("__radius", radius);
+            feature.set_property("__open_route", open_route);
+            if !applied_radii.is_empty() {
+                feature.set_property("__radii", applied_radii.clone());
+            }
+            feature.add_instance_properties(
+                Some(device_name(&instance, index, device_count)),
+                Some(enum_type.clone()),
+            );
+            feature
+        })
+        .collect::<Vec<_>>()
+        .to_collection(Some(instance.clone()), None);
 
     if !instance.is_empty() && save_to_db {
         route::Query::upsert_from_geometry(
@@ -317,8 +442,229 @@ async fn cluster(
     ))
 }
 
+#[post("/recluster/{category}")]
+async fn recluster(
+    conn: web::Data<KojiDb>,
+    jobs: web::Data<Jobs>,
+    url: actix_web::web::Path<String>,
+    payload: web::Json<Args>,
+) -> Result<HttpResponse, Error> {
+    let category = url.into_inner();
+
+    let ArgsUnwrapped {
+        area,
+        benchmark_mode,
+        cluster_mode,
+        cluster_split_level,
+        data_points,
+        data_point_weights,
+        data_point_radii,
+        instance,
+        min_points,
+        radius,
+        radii,
+        return_type,
+        save_to_db,
+        last_seen,
+        sort_by,
+        tth,
+        route_split_level,
+        routing_args,
+        calculation_mode,
+        s2_level,
+        s2_size,
+        parent,
+        max_clusters,
+        clustering_args,
+        center_clusters,
+        genetic_post_processing,
+        genetic_time_budget,
+        seed,
+        weighted,
+        exact_max_points,
+        exact_time_budget,
+        min_coverage,
+        route: route_id,
+        drop_underused,
+        job_id,
+        cooldown_curve,
+        speed,
+        minimize_cycle_time,
+        max_hop_distance,
+        fill_long_hops,
+        exclusions,
+        exclusion_fences,
+        mut route_start,
+        ..
+    } = payload.into_inner().init(Some("recluster"));
+
+    let Some(route_id) = route_id else {
+        return Ok(HttpResponse::BadRequest().json(Response::send_error("no_route")));
+    };
+    let saved = match route::Query::get_one(&conn.koji, route_id.to_string()).await {
+        Ok(saved) => saved,
+        Err(ModelError::Route(_)) => {
+            return Ok(HttpResponse::NotFound().json(Response::send_error("route_not_found")));
+        }
+        Err(err) => return Err(actix_web::error::ErrorInternalServerError(err)),
+    };
+    // a route that was saved as an open path stays one
+    route_start.open |= saved.open_route;
+    let existing = geojson::Geometry::from_json_value(saved.geometry.clone())
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .to_single_vec();
+
+    // falls back to the geofence the route belongs to
+    let area = if area.features.is_empty()
+        && instance.is_empty()
+        && parent.is_none()
+        && data_points.is_empty()
+    {
+        utils::load_collection(&saved.geofence_id.to_string(), &conn).await
+    } else {
+        utils::create_or_find_collection(&instance, &conn, area, &parent, &data_points).await
+    }
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let exclusions = match utils::load_exclusions(exclusions, &exclusion_fences, &conn).await {
+        Ok(exclusions) => exclusions,
+        Err(response) => return Ok(response),
+    };
+    let (data_points, data_point_weights, data_point_radii) = if data_points.is_empty() {
+        let points = utils::points_from_area(&area, &category, &conn, last_seen, tth)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let weights = if weighted {
+            points.get_weights()
+        } else {
+            vec![]
+        };
+        let point_radii = if radii.is_empty() {
+            vec![]
+        } else {
+            points.get_radii(&radii, radius)
+        };
+        (points.to_single_vec(), weights, point_radii)
+    } else {
+        (data_points, data_point_weights, data_point_radii)
+    };
+
+    let mut stats = Stats::new(
+        format!("Recluster | {:?} | {:?}", cluster_mode, calculation_mode),
+        min_points,
+    );
+    stats.set_cycle_model(CycleModel::new(cooldown_curve, speed, minimize_cycle_time));
+    stats.set_hop_limit(HopLimit::new(max_hop_distance, fill_long_hops));
+    stats.set_open_route(route_start.open);
+    let seed = stats.set_seed(seed);
+
+    let job = Jobs::start(&jobs, job_id);
+    let progress = job.progress.clone();
+    let (clusters, added, removed, stats) = web::block(move || {
+        let split = clustering::incremental::split(
+            &existing,
+            &data_points,
+            &data_point_weights,
+            &data_point_radii,
+            radius,
+            min_points,
+            drop_underused,
+        );
+        let uncovered = split.uncovered(&data_points);
+        let added = if uncovered.is_empty() {
+            vec![]
+        } else {
+            let weights = split.uncovered(&data_point_weights);
+            let radii = split.uncovered(&data_point_radii);
+            let input = ClusterInput {
+                data_points: &uncovered,
+                weights: &weights,
+                radii: &radii,
+                radius,
+                min_points,
+                max_clusters: max_clusters.saturating_sub(split.kept.len()),
+                cluster_mode,
+                cluster_split_level,
+                calculation_mode,
+                s2_level,
+                s2_size,
+                area: &area,
+                args: &clustering_args,
+                center_clusters,
+                genetic_post_processing,
+                genetic_time_budget: Duration::from_secs(genetic_time_budget),
+                exact_max_points,
+                exact_time_budget: Duration::from_secs(exact_time_budget),
+                min_coverage,
+                seed,
+                exclusions: &exclusions,
+                progress: &progress,
+            };
+            clustering::main(&input, &mut stats)
+        };
+
+        let clusters = if sort_by == SortBy::Unset {
+            let clusters = stats.fill_long_hops(clustering::incremental::merge(split.kept, &added));
+            stats.distance_stats(&clusters);
+            clusters
+        } else {
+            let mut clusters = split.kept;
+            clusters.extend(added.iter().copied());
+            routing::main(
+                &data_points,
+                clusters,
+                &sort_by,
+                route_split_level,
+                radius,
+                &mut stats,
+                &routing_args,
+                seed,
+                &route_start,
+                &progress,
+            )
+        };
+        stats.set_weights(&data_points, &data_point_weights);
+        stats.set_radii(&data_points, &data_point_radii);
+        stats.cluster_stats(radius, &data_points, &clusters);
+        stats.set_score();
+        (clusters, added, split.removed, stats)
+    })
+    .await?;
+    if let Some(response) = cancelled(job) {
+        return Ok(response);
+    }
+
+    let mut feature = clusters
+        .to_feature(Some(saved.mode.clone()))
+        .remove_last_coord();
+    feature.set_property("__radius", radius);
+    feature.set_property("__added", added);
+    feature.set_property("__removed", removed);
+    feature.set_property("__geofence_id", saved.geofence_id);
+    feature.set_property("__open_route", stats.open_route);
+    feature.add_instance_properties(Some(saved.name.clone()), Some(saved.mode));
+    let feature = feature.to_collection(Some(saved.name.clone()), None);
+
+    if save_to_db {
+        route::Query::upsert_from_geometry(
+            &conn.koji,
+            GeoFormats::FeatureCollection(feature.clone()),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    Ok(utils::response::send(
+        feature,
+        return_type,
+        Some(stats),
+        benchmark_mode,
+        Some(saved.name),
+    ))
+}
+
 #[post("/reroute")]
-async fn reroute(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
+async fn reroute(jobs: web::Data<Jobs>, payload: web::Json<Args>) -> Result<HttpResponse, Error> {
     let ArgsUnwrapped {
         benchmark_mode,
         data_points,
@@ -330,9 +676,21 @@ async fn reroute(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
         sort_by,
         radius,
         routing_args,
+        seed,
+        devices,
+        job_id,
+        cooldown_curve,
+        speed,
+        minimize_cycle_time,
+        max_hop_distance,
+        fill_long_hops,
+        route_start,
         ..
     } = payload.into_inner().init(Some("reroute"));
     let mut stats = Stats::new(String::from("Reroute"), 1);
+    stats.set_cycle_model(CycleModel::new(cooldown_curve, speed, minimize_cycle_time));
+    stats.set_hop_limit(HopLimit::new(max_hop_distance, fill_long_hops));
+    let seed = stats.set_seed(seed);
 
     // For legacy compatibility
     let (clusters, data_points) = if clusters.is_empty() {
@@ -342,18 +700,47 @@ async fn reroute(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
     };
     stats.total_clusters = clusters.len();
 
-    let clusters = routing::main(
-        &data_points,
-        clusters,
-        &sort_by,
-        route_split_level,
-        radius,
-        &mut stats,
-        &routing_args,
-    );
+    let job = Jobs::start(&jobs, job_id);
+    let progress = job.progress.clone();
+    let (routes, stats) = web::block(move || {
+        let routes = if devices > 1 {
+            routing::devices::main(&clusters, devices, &mut stats, &route_start, &progress)
+        } else {
+            vec![routing::main(
+                &data_points,
+                clusters,
+                &sort_by,
+                route_split_level,
+                radius,
+                &mut stats,
+                &routing_args,
+                seed,
+                &route_start,
+                &progress,
+            )]
+        };
+        (routes, stats)
+    })
+    .await?;
+    if let Some(response) = cancelled(job) {
+        return Ok(response);
+    }
 
-    let feature = clusters.to_feature(Some(mode.clone())).remove_last_coord();
-    let feature = feature.to_collection(Some(instance.clone()), Some(mode));
+    let device_count = routes.len();
+    let open_route = stats.open_route;
+    let feature = routes
+        .into_iter()
+        .enumerate()
+        .map(|(index, clusters)| {
+            let mut feature = clusters.to_feature(Some(mode.clone())).remove_last_coord();
+            feature.set_property("__open_route", open_route);
+            if device_count > 1 {
+                feature.set_property("__name", device_name(&instance, index, device_count));
+            }
+            feature
+        })
+        .collect::<Vec<_>>()
+        .to_collection(Some(instance.clone()), Some(mode));
 
     Ok(utils::response::send(
         feature,
@@ -364,15 +751,91 @@ async fn reroute(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
     ))
 }
 
+/// Routes that were split between devices are saved separately, numbered from 1
+fn device_name(instance: &str, index: usize, device_count: usize) -> String {
+    if device_count > 1 && !instance.is_empty() {
+        format!("{}_{}", instance, index + 1)
+    } else {
+        instance.to_string()
+    }
+}
+
+/// Responds with an error when the job was cancelled while it was running
+fn cancelled(job: Job) -> Option<HttpResponse> {
+    if job.progress.is_cancelled() {
+        Some(HttpResponse::BadRequest().json(Response::send_error("cancelled")))
+    } else {
+        job.finish();
+        None
+    }
+}
+
+/// Streams the phase and percentage of a running job as server-sent events,
+/// a final `done` event is sent once the job is no longer running
+#[get("/progress/{job_id}")]
+async fn job_progress(jobs: web::Data<Jobs>, url: actix_web::web::Path<String>) -> HttpResponse {
+    let job_id = url.into_inner();
+
+    // the stream may be opened before the request that starts the job arrives
+    let events = futures::stream::unfold(Some((false, 0)), move |state| {
+        let jobs = jobs.clone();
+        let job_id = job_id.clone();
+        async move {
+            let (seen, waited) = state?;
+            actix_web::rt::time::sleep(PROGRESS_INTERVAL).await;
+            match jobs.get(&job_id) {
+                Some(progress) => Some((
+                    format!("data: {}\n\n", json!(progress.snapshot())),
+                    Some((true, 0)),
+                )),
+                None if seen || waited >= PROGRESS_WAIT => {
+                    Some(("event: done\ndata: {}\n\n".to_string(), None))
+                }
+                None => Some((": waiting\n\n".to_string(), Some((false, waited + 1)))),
+            }
+        }
+    })
+    .map(|event| Ok::<_, Error>(web::Bytes::from(event)));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        // compressing would hold the events back until the stream ends
+        .insert_header(ContentEncoding::Identity)
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events)
+}
+
+#[delete("/progress/{job_id}")]
+async fn cancel_job(jobs: web::Data<Jobs>, url: actix_web::web::Path<String>) -> HttpResponse {
+    if jobs.cancel(&url.into_inner()) {
+        HttpResponse::Ok().json(Response {
+            data: None,
+            message: "Success".to_string(),
+            status: "ok".to_string(),
+            stats: None,
+            status_code: 200,
+        })
+    } else {
+        HttpResponse::NotFound().json(Response::send_error("job_not_found"))
+    }
+}
+
 #[post("/route-stats")]
 async fn route_stats(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
     let ArgsUnwrapped {
         clusters,
         data_points,
+        data_point_weights,
+        data_point_radii,
         instance,
         radius,
         mode,
         min_points,
+        cooldown_curve,
+        speed,
+        minimize_cycle_time,
+        max_hop_distance,
+        route_start,
         ..
     } = payload.into_inner().init(Some("route-stats"));
 
@@ -381,9 +844,14 @@ async fn route_stats(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
             .json(Response::send_error("no_clusters_or_data_points_found")));
     }
     let mut stats = Stats::new(format!("Route Stats | {:?}", mode), min_points);
+    stats.set_cycle_model(CycleModel::new(cooldown_curve, speed, minimize_cycle_time));
+    stats.set_hop_limit(HopLimit::new(max_hop_distance, false));
+    stats.set_open_route(route_start.open);
 
     stats.distance_stats(&clusters);
     if !data_points.is_empty() {
+        stats.set_weights(&data_points, &data_point_weights);
+        stats.set_radii(&data_points, &data_point_radii);
         stats.cluster_stats(radius, &data_points, &clusters);
         stats.set_score();
     }
@@ -409,14 +877,23 @@ async fn route_stats_category(
     let ArgsUnwrapped {
         clusters,
         data_points,
+        data_point_weights,
+        data_point_radii,
         instance,
         radius,
+        radii,
         mode,
         area,
         parent,
         last_seen,
         tth,
         min_points,
+        weighted,
+        cooldown_curve,
+        speed,
+        minimize_cycle_time,
+        max_hop_distance,
+        route_start,
         ..
     } = payload.into_inner().init(Some("route-stats"));
     let category = url.into_inner();
@@ -425,13 +902,23 @@ async fn route_stats_category(
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let data_points = if !data_points.is_empty() {
-        data_points
+    let (data_points, data_point_weights, data_point_radii) = if !data_points.is_empty() {
+        (data_points, data_point_weights, data_point_radii)
     } else {
-        utils::points_from_area(&area, &category, &conn, last_seen, tth)
+        let points = utils::points_from_area(&area, &category, &conn, last_seen, tth)
             .await
-            .map_err(actix_web::error::ErrorInternalServerError)?
-            .to_single_vec()
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let weights = if weighted {
+            points.get_weights()
+        } else {
+            vec![]
+        };
+        let point_radii = if radii.is_empty() {
+            vec![]
+        } else {
+            points.get_radii(&radii, radius)
+        };
+        (points.to_single_vec(), weights, point_radii)
     };
 
     if clusters.is_empty() && data_points.is_empty() {
@@ -440,9 +927,14 @@ async fn route_stats_category(
     }
 
     let mut stats = Stats::new(format!("Route Stats | {:?}", mode), min_points);
+    stats.set_cycle_model(CycleModel::new(cooldown_curve, speed, minimize_cycle_time));
+    stats.set_hop_limit(HopLimit::new(max_hop_distance, false));
+    stats.set_open_route(route_start.open);
 
     stats.distance_stats(&clusters);
     if !data_points.is_empty() {
+        stats.set_weights(&data_points, &data_point_weights);
+        stats.set_radii(&data_points, &data_point_radii);
         stats.cluster_stats(radius, &data_points, &clusters);
         stats.set_score();
     }
@@ -459,6 +951,170 @@ async fn route_stats_category(
     ))
 }
 
+#[post("/route-diff")]
+async fn route_diff(
+    conn: web::Data<KojiDb>,
+    payload: web::Json<Args>,
+) -> Result<HttpResponse, Error> {
+    diff_routes(conn, payload.into_inner(), None).await
+}
+
+#[post("/route-diff/{category}")]
+async fn route_diff_category(
+    conn: web::Data<KojiDb>,
+    url: actix_web::web::Path<String>,
+    payload: web::Json<Args>,
+) -> Result<HttpResponse, Error> {
+    diff_routes(conn, payload.into_inner(), Some(url.into_inner())).await
+}
+
+/// Without a category only the data points of the request are used for the coverage stats
+async fn diff_routes(
+    conn: web::Data<KojiDb>,
+    payload: Args,
+    category: Option<String>,
+) -> Result<HttpResponse, Error> {
+    let ArgsUnwrapped {
+        routes,
+        data_points,
+        data_point_weights,
+        data_point_radii,
+        instance,
+        radius,
+        radii,
+        mode,
+        area,
+        parent,
+        last_seen,
+        tth,
+        min_points,
+        weighted,
+        cooldown_curve,
+        speed,
+        minimize_cycle_time,
+        max_hop_distance,
+        route_start,
+        ..
+    } = payload.init(Some("route-diff"));
+
+    let Ok(routes) = <[RouteRef; 2]>::try_from(routes) else {
+        return Ok(HttpResponse::BadRequest().json(Response::send_error("two_routes_required")));
+    };
+    let mut geofence_id = None;
+    let mut clusters = vec![];
+    let mut open_routes = vec![];
+    for route in routes {
+        clusters.push(match route {
+            RouteRef::Saved(id) => {
+                let saved = match route::Query::get_one(&conn.koji, id.to_string()).await {
+                    Ok(saved) => saved,
+                    Err(ModelError::Route(_)) => {
+                        return Ok(HttpResponse::NotFound()
+                            .json(Response::send_error("route_not_found")));
+                    }
+                    Err(err) => return Err(actix_web::error::ErrorInternalServerError(err)),
+                };
+                geofence_id = geofence_id.or(Some(saved.geofence_id));
+                // a route that was saved as an open path is measured as one
+                open_routes.push(route_start.open || saved.open_route);
+                geojson::Geometry::from_json_value(saved.geometry)
+                    .map_err(actix_web::error::ErrorInternalServerError)?
+                    .to_single_vec()
+            }
+            RouteRef::Clusters(clusters) => {
+                open_routes.push(route_start.open);
+                clusters
+            }
+        });
+    }
+
+    let (data_points, data_point_weights, data_point_radii) = if !data_points.is_empty() {
+        (data_points, data_point_weights, data_point_radii)
+    } else if let Some(category) = category {
+        // falls back to the geofence of the first saved route
+        let area = if area.features.is_empty()
+            && instance.is_empty()
+            && parent.is_none()
+            && let Some(geofence_id) = geofence_id
+        {
+            utils::load_collection(&geofence_id.to_string(), &conn).await
+        } else {
+            utils::create_or_find_collection(&instance, &conn, area, &parent, &data_points).await
+        }
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+        let points = utils::points_from_area(&area, &category, &conn, last_seen, tth)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let weights = if weighted {
+            points.get_weights()
+        } else {
+            vec![]
+        };
+        let point_radii = if radii.is_empty() {
+            vec![]
+        } else {
+            points.get_radii(&radii, radius)
+        };
+        (points.to_single_vec(), weights, point_radii)
+    } else {
+        (data_points, data_point_weights, data_point_radii)
+    };
+
+    let stats: Vec<Stats> = clusters
+        .iter()
+        .enumerate()
+        .map(|(index, clusters)| {
+            let mut stats =
+                Stats::new(format!("Route Diff {} | {:?}", index + 1, mode), min_points);
+            stats.set_cycle_model(CycleModel::new(
+                cooldown_curve.clone(),
+                speed,
+                minimize_cycle_time,
+            ));
+            stats.set_hop_limit(HopLimit::new(max_hop_distance, false));
+            stats.set_open_route(open_routes[index]);
+            stats.distance_stats(clusters);
+            if !data_points.is_empty() {
+                stats.set_weights(&data_points, &data_point_weights);
+                stats.set_radii(&data_points, &data_point_radii);
+                stats.cluster_stats(radius, &data_points, clusters);
+                stats.set_score();
+            }
+            stats.log(Some(instance.clone()));
+            stats
+        })
+        .collect();
+    let diff = clustering::incremental::diff(
+        &clusters[0],
+        &clusters[1],
+        &data_points,
+        &data_point_radii,
+        radius,
+    );
+    log::info!(
+        "[ROUTE_DIFF] {} clusters added, {} removed | {} data points only covered by the first route, {} only by the second",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.only_first.len(),
+        diff.only_second.len()
+    );
+
+    Ok(HttpResponse::Ok().json(Response {
+        data: Some(json!({
+            "first": { "clusters": clusters[0], "stats": stats[0] },
+            "second": { "clusters": clusters[1], "stats": stats[1] },
+            "added": diff.added,
+            "removed": diff.removed,
+            "only_first": diff.only_first,
+            "only_second": diff.only_second,
+        })),
+        message: "Success".to_string(),
+        status: "ok".to_string(),
+        stats: None,
+        status_code: 200,
+    }))
+}
+
 #[post("/area")]
 async fn calculate_area(payload: web::Json<Args>) -> Result<HttpResponse, Error> {
     let ArgsUnwrapped { area, .. } = payload.into_inner().init(Some("calculate_area"));
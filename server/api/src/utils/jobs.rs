@@ -0,0 +1,75 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use actix_web::web;
+use algorithms::progress::Progress;
+
+/// Calculations that were started with a `job_id`, shared between all workers
+#[derive(Debug, Default)]
+pub struct Jobs(Mutex<HashMap<String, Progress>>);
+
+impl Jobs {
+    /// Registers the calculation when it has an ID, the returned guard unregisters it again
+    pub fn start(jobs: &web::Data<Jobs>, id: Option<String>) -> Job {
+        let progress = Progress::default();
+        if let Some(id) = id.as_ref()
+            && let Ok(mut jobs) = jobs.0.lock()
+            && let Some(previous) = jobs.insert(id.clone(), progress.clone())
+        {
+            log::warn!("job {} was started again, cancelling the previous run", id);
+            previous.cancel();
+        }
+        Job {
+            jobs: jobs.clone(),
+            id,
+            progress,
+            finished: false,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Progress> {
+        self.0.lock().ok()?.get(id).cloned()
+    }
+
+    /// Returns false when no calculation with this ID is running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.get(id) {
+            Some(progress) => {
+                progress.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Cancels the calculation if it's dropped before [Job::finish] was called,
+/// which is what happens to the request future when the client disconnects
+pub struct Job {
+    jobs: web::Data<Jobs>,
+    id: Option<String>,
+    pub progress: Progress,
+    finished: bool,
+}
+
+impl Job {
+    pub fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.progress.cancel();
+        }
+        // a newer run with the same ID may have taken its place
+        if let Some(id) = self.id.as_ref()
+            && let Ok(mut jobs) = self.jobs.0.lock()
+            && jobs
+                .get(id)
+                .is_some_and(|progress| progress.same_as(&self.progress))
+        {
+            jobs.remove(id);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 
+use algorithms::exclusion::Exclusions;
 use geo::Point;
 use geojson::{Geometry, Value};
 use model::{
@@ -15,6 +16,7 @@ use model::{
 
 pub mod auth;
 pub mod error;
+pub mod jobs;
 pub mod request;
 pub mod response;
 
@@ -92,6 +94,57 @@ pub async fn create_or_find_collection(
     }
 }
 
+/// The exclusion areas of the request, merged with the saved geofences it names.
+/// A fence that doesn't exist, can't be loaded or isn't tagged as an exclusion fails the request
+/// instead of being skipped, calculating without a no-go zone would put centers inside of it
+pub async fn load_exclusions(
+    exclusions: FeatureCollection,
+    exclusion_fences: &Vec<UnknownId>,
+    conn: &KojiDb,
+) -> Result<Exclusions, HttpResponse> {
+    let mut features = exclusions.features;
+    for fence in exclusion_fences {
+        match geofence::Query::get_one_feature(
+            &conn.koji,
+            fence.to_string(),
+            &ApiQueryArgs::default(),
+        )
+        .await
+        {
+            Ok(feature) if is_exclusion(&feature) => features.push(feature),
+            Ok(_) => {
+                return Err(HttpResponse::BadRequest().json(response::Response::send_error(
+                    &format!("exclusion_fence_not_tagged: {}", fence.to_string()),
+                )));
+            }
+            Err(ModelError::Geofence(_)) => {
+                return Err(HttpResponse::NotFound().json(response::Response::send_error(
+                    &format!("exclusion_fence_not_found: {}", fence.to_string()),
+                )));
+            }
+            Err(err) => {
+                log::error!("Unable to load exclusion {}: {:?}", fence.to_string(), err);
+                return Err(HttpResponse::InternalServerError().json(
+                    response::Response::send_error(&format!(
+                        "exclusion_fence_not_loaded: {}",
+                        fence.to_string()
+                    )),
+                ));
+            }
+        }
+    }
+    Ok(Exclusions::new(&features.to_collection(None, None)))
+}
+
+fn is_exclusion(feature: &Feature) -> bool {
+    match feature.property("exclusion") {
+        Some(serde_json::Value::Bool(value)) => *value,
+        Some(serde_json::Value::String(value)) => value == "true" || value == "1",
+        Some(serde_json::Value::Number(value)) => value.as_f64().is_some_and(|n| n != 0.),
+        _ => false,
+    }
+}
+
 pub async fn points_from_area(
     area: &FeatureCollection,
     category: &String,
@@ -1,7 +1,9 @@
 use super::*;
 
+use std::collections::BTreeMap;
+
 use actix_web::HttpResponse;
-use algorithms::stats::Stats;
+use algorithms::{plugin::manifest::Manifest, stats::Stats};
 use geojson::JsonValue;
 use model::api::{Precision, ToGeometry, ToSql};
 use serde::Serialize;
@@ -23,6 +25,9 @@ pub struct ConfigResponse {
     pub route_plugins: Vec<String>,
     pub clustering_plugins: Vec<String>,
     pub bootstrap_plugins: Vec<String>,
+    pub route_plugin_manifests: BTreeMap<String, Manifest>,
+    pub clustering_plugin_manifests: BTreeMap<String, Manifest>,
+    pub bootstrap_plugin_manifests: BTreeMap<String, Manifest>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230117_010422_routes_table::Route;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        log::info!("[MIGRATION_21] Creating Route Open Route Column");
+        let table = Table::alter()
+            .table(Route::Table)
+            .add_column(
+                ColumnDef::new(Alias::new("open_route"))
+                    .boolean()
+                    .not_null()
+                    .default(false),
+            )
+            .to_owned();
+
+        manager.alter_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        log::info!("[MIGRATION_21] Dropping Route Open Route Column");
+        let table = Table::alter()
+            .table(Route::Table)
+            .drop_column(Alias::new("open_route"))
+            .to_owned();
+
+        manager.alter_table(table).await
+    }
+}
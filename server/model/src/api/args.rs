@@ -1,10 +1,21 @@
 use super::{calc_mode::CalculationMode, cluster_mode::ClusterMode, sort_by::SortBy, *};
 
+use std::collections::HashMap;
+
 use crate::{
     api::text::TextHelpers,
     utils::{get_enum, get_enum_by_geometry_string},
 };
 
+/// Longest the genetic post processing may block a worker for, in seconds
+const MAX_GENETIC_TIME_BUDGET: u64 = 120;
+/// Longest the exact cluster mode may search for, in seconds
+const MAX_EXACT_TIME_BUDGET: u64 = 120;
+/// Longest the lattice search may run for per area, in seconds
+const MAX_LATTICE_TIME_BUDGET: u64 = 60;
+/// Shortest `max_hop_distance` in meters, shorter ones would fill hops with countless waypoints
+const MIN_HOP_DISTANCE: Precision = 10.;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Auth {
     pub password: String,
@@ -181,15 +192,82 @@ pub enum SpawnpointTth {
     Unknown,
 }
 
+/// A data point with an optional weight and radius, points without a weight weigh `1.0`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DataPointStruct {
+    pub lat: Precision,
+    pub lon: Precision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<Precision>,
+    /// Overrides the `radius` arg for this point, in meters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<Precision>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum DataPointsArg {
     Array(single_vec::SingleVec),
-    Struct(single_struct::SingleStruct),
+    Struct(Vec<DataPointStruct>),
     Feature(Feature),
     FeatureCollection(FeatureCollection),
 }
 
+/// Where a route starts, either the index of a cluster as it was sent or a `[lat, lon]` coordinate
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum StartArg {
+    Index(usize),
+    Point(point_array::PointArray),
+}
+
+impl StartArg {
+    /// Resolves an index against the unsorted clusters
+    fn point(&self, clusters: &single_vec::SingleVec) -> Option<point_array::PointArray> {
+        match self {
+            StartArg::Index(index) => {
+                let point = clusters.get(*index).copied();
+                if point.is_none() {
+                    log::warn!(
+                        "index {} is out of bounds for {} clusters",
+                        index,
+                        clusters.len()
+                    );
+                }
+                point
+            }
+            StartArg::Point(point) => Some(*point),
+        }
+    }
+}
+
+/// How the start, end and order of a route are chosen
+#[derive(Debug, Clone, Default)]
+pub struct RouteStart {
+    pub start: Option<StartArg>,
+    /// Only used by open routes, closed ones return to their start
+    pub end: Option<StartArg>,
+    pub preserve_order: bool,
+    /// Routes a one-way path instead of a closed loop
+    pub open: bool,
+}
+
+impl RouteStart {
+    /// Coordinate the route should start closest to, an index is resolved against the unsorted clusters
+    pub fn point(&self, clusters: &single_vec::SingleVec) -> Option<point_array::PointArray> {
+        self.start.as_ref()?.point(clusters)
+    }
+
+    /// Coordinate an open route should end closest to, an index is resolved against the unsorted clusters
+    pub fn end_point(&self, clusters: &single_vec::SingleVec) -> Option<point_array::PointArray> {
+        if self.open {
+            self.end.as_ref()?.point(clusters)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum UnknownId {
@@ -197,6 +275,21 @@ pub enum UnknownId {
     Number(u32),
 }
 
+/// A route to compare, either the ID or name of a saved route or its clusters
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum RouteArg {
+    Saved(UnknownId),
+    Inline(DataPointsArg),
+}
+
+/// [RouteArg] with the sent clusters resolved
+#[derive(Debug, Clone)]
+pub enum RouteRef {
+    Saved(UnknownId),
+    Clusters(single_vec::SingleVec),
+}
+
 impl ToString for UnknownId {
     fn to_string(&self) -> String {
         match self {
@@ -247,17 +340,19 @@ pub struct Args {
     /// Data points to cluster or reroute.
     /// Overrides any inputted area.
     ///
+    /// Points can be weighted with a `weight` key on structs or a `weight` property on features,
+    /// the same goes for a per point `radius`
+    ///
     /// Accepts [DataPointsArg]
     pub data_points: Option<DataPointsArg>,
     /// Clusters to run through the stat producer.
     ///
     /// Accepts [DataPointsArg]
     pub clusters: Option<DataPointsArg>,
-    /// Number of devices to use in VRP routing
+    /// Number of devices to split the route between, each gets its own closed route
+    /// of roughly the same length. When above `1`, the native TSP solver is used and `sort_by` is ignored
     ///
     /// Default: `1`
-    ///
-    /// Deprecated
     pub devices: Option<usize>,
     /// The maximum amount of clusters to return
     ///
@@ -306,6 +401,12 @@ pub struct Args {
     ///
     /// Default: `70`
     pub radius: Option<Precision>,
+    /// Radius per data point category, in meters, e.g. `{ "gym": 80, "pokestop": 70, "station": 60 }`.
+    /// Mostly useful for the `fort` category, a cluster only counts a point that is within the point's own radius.
+    /// Categories that are not listed use `radius`
+    ///
+    /// Default: `{}`
+    pub radii: Option<HashMap<String, Precision>>,
     /// The return type for the data
     ///
     /// Accepts [ReturnTypeArg]
@@ -388,6 +489,123 @@ pub struct Args {
     ///
     /// Default: `false`
     pub genetic_post_processing: Option<bool>,
+    /// Wall-clock time, in seconds, that the genetic post processing is allowed to run for, at most `120`
+    ///
+    /// Default: `10`
+    pub genetic_time_budget: Option<u64>,
+    /// Searches the rotation and phase offset of the hex lattice when bootstrapping with [CalculationMode::Radius],
+    /// keeping the arrangement with the fewest circles that still covers the area
+    ///
+    /// Default: `false`
+    pub optimize_lattice: Option<bool>,
+    /// Wall-clock time, in seconds, that the lattice search is allowed to run for per area, at most `60`
+    ///
+    /// Default: `5`
+    pub lattice_time_budget: Option<u64>,
+    /// Areas that no bootstrap circle or cluster may have its center in, like lakes or airports.
+    /// Centers that would end up inside are moved just outside of them when they still cover what was near the edge
+    ///
+    /// Accepts an optional [GeoFormats]
+    ///
+    /// Default: `None`
+    pub exclusions: Option<GeoFormats>,
+    /// The IDs or names of saved geofences to use as exclusions, only geofences with a truthy `exclusion` property are used
+    ///
+    /// Default: `[]`
+    pub exclusion_fences: Option<Vec<UnknownId>>,
+    /// Seed for every randomized step: the candidate grid jitter, `random` sorting,
+    /// centering clusters and the genetic post processing.
+    /// The seed that was used is returned in the stats, sending it back returns the same route
+    /// unless the genetic post processing or the `exact` cluster mode ran out of time
+    ///
+    /// Default: `None` (random)
+    pub seed: Option<u64>,
+    /// Weights the data points pulled from the database so clustering favours high-value points.
+    /// Unconfirmed spawnpoints weigh half as much and EX raid eligible gyms count double.
+    /// Weights supplied with `data_points` are always used
+    ///
+    /// Default: `false`
+    pub weighted: Option<bool>,
+    /// Largest number of data points the `exact` cluster mode will attempt,
+    /// larger inputs fall back to the `best` strategy
+    ///
+    /// Default: `500`
+    pub exact_max_points: Option<usize>,
+    /// Wall-clock time, in seconds, that the `exact` cluster mode searches for before it returns
+    /// the best clusters found so far, at most `120`
+    ///
+    /// Default: `60`
+    pub exact_time_budget: Option<u64>,
+    /// Minimum number of clusters every data point should sit inside of,
+    /// extra clusters are placed until this is met or `max_clusters` is reached
+    ///
+    /// Default: `1`
+    pub min_coverage: Option<usize>,
+    /// The ID or name of a saved route, used by `/calc/recluster` as the fixed clusters to build on
+    ///
+    /// Default: `None`
+    pub route: Option<UnknownId>,
+    /// Removes clusters of the saved route that no longer cover `min_points` when reclustering
+    ///
+    /// Default: `false`
+    pub drop_underused: Option<bool>,
+    /// Client chosen ID that the progress of the calculation can be followed with
+    /// on `GET /calc/progress/{job_id}` and cancelled with on `DELETE /calc/progress/{job_id}`
+    ///
+    /// Default: `None`
+    pub job_id: Option<String>,
+    /// Cooldown per hop distance as `[meters, seconds]` pairs, linearly interpolated between them,
+    /// the last pair applies to anything further. Used for the cycle time stats
+    ///
+    /// Default: the game's teleport cooldowns
+    pub cooldown_curve: Option<Vec<[Precision; 2]>>,
+    /// Meters per second a device moves at, a hop takes at least its distance divided by this
+    ///
+    /// Default: `None`, devices teleport
+    pub speed: Option<Precision>,
+    /// Makes the native TSP solver minimize the cycle time instead of the distance
+    ///
+    /// Default: `false`
+    pub minimize_cycle_time: Option<bool>,
+    /// Starts the route at this cluster instead of one of the best clusters.
+    /// A number is the index of a cluster as it was sent, `[lat, lon]` starts at the closest cluster
+    ///
+    /// Accepts [StartArg]
+    ///
+    /// Default: `None`
+    pub start: Option<StartArg>,
+    /// Keeps the clusters in the order they were sent, skipping `sort_by`.
+    /// The route is only rotated when `start` is set and it is not an open route
+    ///
+    /// Default: `false`
+    pub preserve_order: Option<bool>,
+    /// Routes a one-way path between two areas instead of a closed loop.
+    /// The path is optimized as such and its distance leaves out the hop back to the start
+    ///
+    /// Default: `false`
+    pub open_route: Option<bool>,
+    /// Ends an open route at this cluster, takes the same values as `start`
+    ///
+    /// Accepts [StartArg]
+    ///
+    /// Default: `None`
+    pub end: Option<StartArg>,
+    /// Meters a hop should not exceed, at least `10`. The native TSP solver avoids longer hops
+    /// and the ones left are listed in the `long_hops` stats
+    ///
+    /// Default: `None`
+    pub max_hop_distance: Option<Precision>,
+    /// Splits hops longer than `max_hop_distance` with evenly spaced filler waypoints
+    /// instead of only listing them
+    ///
+    /// Default: `false`
+    pub fill_long_hops: Option<bool>,
+    /// The two routes `/calc/route-diff` compares
+    ///
+    /// Accepts [RouteArg]
+    ///
+    /// Default: `None`
+    pub routes: Option<Vec<RouteArg>>,
 }
 
 pub struct ArgsUnwrapped {
@@ -399,11 +617,14 @@ pub struct ArgsUnwrapped {
     pub max_clusters: usize,
     pub clusters: single_vec::SingleVec,
     pub data_points: single_vec::SingleVec,
+    pub data_point_weights: Vec<Precision>,
+    pub data_point_radii: Vec<Precision>,
     pub devices: usize,
     pub generations: usize,
     pub instance: String,
     pub min_points: usize,
     pub radius: Precision,
+    pub radii: HashMap<String, Precision>,
     pub return_type: ReturnTypeArg,
     pub parent: Option<UnknownId>,
     pub last_seen: u32,
@@ -422,6 +643,26 @@ pub struct ArgsUnwrapped {
     pub bootstrapping_args: String,
     pub center_clusters: bool,
     pub genetic_post_processing: bool,
+    pub genetic_time_budget: u64,
+    pub optimize_lattice: bool,
+    pub lattice_time_budget: u64,
+    pub exclusions: FeatureCollection,
+    pub exclusion_fences: Vec<UnknownId>,
+    pub seed: Option<u64>,
+    pub weighted: bool,
+    pub exact_max_points: usize,
+    pub exact_time_budget: u64,
+    pub min_coverage: usize,
+    pub route: Option<UnknownId>,
+    pub drop_underused: bool,
+    pub job_id: Option<String>,
+    pub cooldown_curve: Vec<[Precision; 2]>,
+    pub speed: Option<Precision>,
+    pub minimize_cycle_time: bool,
+    pub route_start: RouteStart,
+    pub max_hop_distance: Option<Precision>,
+    pub fill_long_hops: bool,
+    pub routes: Vec<RouteRef>,
 }
 
 fn validate_s2_cell(value_to_check: Option<u64>, label: &str) -> u64 {
@@ -441,13 +682,43 @@ fn validate_s2_cell(value_to_check: Option<u64>, label: &str) -> u64 {
     }
 }
 
-fn resolve_data_points(data_points: Option<DataPointsArg>) -> single_vec::SingleVec {
+/// A data point along with the optional weight and radius that it was sent with
+type ResolvedPoint = (
+    point_array::PointArray,
+    Option<Precision>,
+    Option<Precision>,
+);
+
+/// Flattens features into their points, each point takes the `weight` and `radius` properties of its feature
+fn resolve_feature_points(features: Vec<Feature>) -> Vec<ResolvedPoint> {
+    features
+        .into_iter()
+        .flat_map(|feature| {
+            let weight = feature.property("weight").and_then(|w| w.as_f64());
+            let radius = feature.property("radius").and_then(|r| r.as_f64());
+            feature
+                .to_single_vec()
+                .into_iter()
+                .map(move |point| (point, weight, radius))
+        })
+        .collect()
+}
+
+fn resolve_data_points(data_points: Option<DataPointsArg>) -> Vec<ResolvedPoint> {
     if let Some(data_points) = data_points {
         match data_points {
-            DataPointsArg::Struct(data_points) => data_points.to_single_vec(),
-            DataPointsArg::Array(data_points) => data_points,
-            DataPointsArg::Feature(data_points) => data_points.to_single_vec(),
-            DataPointsArg::FeatureCollection(data_points) => data_points.to_single_vec(),
+            DataPointsArg::Struct(data_points) => data_points
+                .into_iter()
+                .map(|p| ([p.lat, p.lon], p.weight, p.radius))
+                .collect(),
+            DataPointsArg::Array(data_points) => data_points
+                .into_iter()
+                .map(|point| (point, None, None))
+                .collect(),
+            DataPointsArg::Feature(data_points) => resolve_feature_points(vec![data_points]),
+            DataPointsArg::FeatureCollection(data_points) => {
+                resolve_feature_points(data_points.features)
+            }
         }
     } else {
         vec![]
@@ -476,6 +747,7 @@ impl Args {
             instance,
             min_points,
             radius,
+            radii,
             return_type,
             routing_time,
             only_unique,
@@ -496,6 +768,29 @@ impl Args {
             bootstrapping_args,
             center_clusters,
             genetic_post_processing,
+            genetic_time_budget,
+            optimize_lattice,
+            lattice_time_budget,
+            exclusions,
+            exclusion_fences,
+            seed,
+            weighted,
+            exact_max_points,
+            exact_time_budget,
+            min_coverage,
+            route,
+            drop_underused,
+            job_id,
+            cooldown_curve,
+            speed,
+            minimize_cycle_time,
+            start,
+            preserve_order,
+            open_route,
+            end,
+            max_hop_distance,
+            fill_long_hops,
+            routes,
         } = self;
         let enum_type = get_enum_by_geometry_string(geometry_type);
         let (area, default_return_type) = if let Some(area) = area {
@@ -546,6 +841,18 @@ impl Args {
         let instance = instance.unwrap_or("".to_string());
         let min_points = min_points.unwrap_or(1);
         let radius = radius.unwrap_or(70.0);
+        let radii = radii.unwrap_or_default();
+        let data_point_weights = if data_points.iter().any(|p| p.1.is_some()) {
+            data_points.iter().map(|p| p.1.unwrap_or(1.)).collect()
+        } else {
+            vec![]
+        };
+        let data_point_radii = if data_points.iter().any(|p| p.2.is_some()) {
+            data_points.iter().map(|p| p.2.unwrap_or(radius)).collect()
+        } else {
+            vec![]
+        };
+        let data_points = data_points.into_iter().map(|p| p.0).collect();
         let return_type = if let Some(return_type) = return_type {
             get_return_type(return_type, &default_return_type)
         } else {
@@ -562,7 +869,13 @@ impl Args {
         };
         let center_clusters = center_clusters.unwrap_or(false);
         let genetic_post_processing = genetic_post_processing.unwrap_or_default();
-        let clusters = resolve_data_points(clusters);
+        let genetic_time_budget = genetic_time_budget
+            .unwrap_or(10)
+            .min(MAX_GENETIC_TIME_BUDGET);
+        let clusters = resolve_data_points(clusters)
+            .into_iter()
+            .map(|p| p.0)
+            .collect();
         let last_seen = last_seen.unwrap_or(0);
         let save_to_db = save_to_db.unwrap_or(false);
         let save_to_scanner = save_to_scanner.unwrap_or(false);
@@ -602,12 +915,15 @@ impl Args {
             calculation_mode,
             s2_size,
             data_points,
+            data_point_weights,
+            data_point_radii,
             devices,
             generations,
             parent,
             instance,
             min_points,
             radius,
+            radii,
             return_type,
             last_seen,
             save_to_db,
@@ -623,6 +939,49 @@ impl Args {
             bootstrapping_args,
             center_clusters,
             genetic_post_processing,
+            genetic_time_budget,
+            optimize_lattice: optimize_lattice.unwrap_or(false),
+            lattice_time_budget: lattice_time_budget
+                .unwrap_or(5)
+                .min(MAX_LATTICE_TIME_BUDGET),
+            exclusions: exclusions
+                .map(|exclusions| exclusions.to_collection(None, None))
+                .unwrap_or_default(),
+            exclusion_fences: exclusion_fences.unwrap_or_default(),
+            seed,
+            weighted: weighted.unwrap_or(false),
+            exact_max_points: exact_max_points.unwrap_or(500),
+            exact_time_budget: exact_time_budget.unwrap_or(60).min(MAX_EXACT_TIME_BUDGET),
+            min_coverage: min_coverage.unwrap_or(1).max(1),
+            route,
+            drop_underused: drop_underused.unwrap_or(false),
+            job_id,
+            cooldown_curve: cooldown_curve.unwrap_or_default(),
+            speed,
+            minimize_cycle_time: minimize_cycle_time.unwrap_or(false),
+            route_start: RouteStart {
+                start,
+                end,
+                preserve_order: preserve_order.unwrap_or(false),
+                open: open_route.unwrap_or(false),
+            },
+            max_hop_distance: max_hop_distance
+                .filter(|distance| *distance > 0.)
+                .map(|distance| distance.max(MIN_HOP_DISTANCE)),
+            fill_long_hops: fill_long_hops.unwrap_or(false),
+            routes: routes
+                .unwrap_or_default()
+                .into_iter()
+                .map(|route| match route {
+                    RouteArg::Saved(id) => RouteRef::Saved(id),
+                    RouteArg::Inline(clusters) => RouteRef::Clusters(
+                        resolve_data_points(Some(clusters))
+                            .into_iter()
+                            .map(|p| p.0)
+                            .collect(),
+                    ),
+                })
+                .collect(),
         }
     }
 }
@@ -8,6 +8,7 @@ pub enum ClusterMode {
     Balanced,
     Better,
     Best,
+    Exact,
     Custom(String),
 }
 
@@ -24,6 +25,7 @@ impl<'de> Deserialize<'de> for ClusterMode {
             "balanced" => Ok(ClusterMode::Balanced),
             "better" => Ok(ClusterMode::Better),
             "best" => Ok(ClusterMode::Best),
+            "exact" => Ok(ClusterMode::Exact),
             "bruteforce" => {
                 log::warn!("bruteforce is now deprecated, using `better` strategy instead");
                 Ok(ClusterMode::Better)
@@ -45,7 +47,8 @@ impl PartialEq for ClusterMode {
             | (ClusterMode::Fast, ClusterMode::Fast)
             | (ClusterMode::Balanced, ClusterMode::Balanced)
             | (ClusterMode::Better, ClusterMode::Better)
-            | (ClusterMode::Best, ClusterMode::Best) => true,
+            | (ClusterMode::Best, ClusterMode::Best)
+            | (ClusterMode::Exact, ClusterMode::Exact) => true,
             _ => false,
         }
     }
@@ -62,6 +65,7 @@ impl ToString for ClusterMode {
             ClusterMode::Balanced => "Balanced",
             ClusterMode::Better => "Better",
             ClusterMode::Best => "Best",
+            ClusterMode::Exact => "Exact",
             ClusterMode::Custom(s) => s,
         }
         .to_string()
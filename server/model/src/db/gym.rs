@@ -64,14 +64,15 @@ impl Query {
             .select_only()
             .column(Column::Lat)
             .column(Column::Lon)
+            .column(Column::ExRaidEligible)
             .filter(Column::Updated.gt(last_seen))
             .filter(Column::Deleted.eq(false))
             .filter(Column::Enabled.eq(true))
             .limit(2_000_000)
-            .into_model::<api::point_struct::PointStruct>()
+            .into_model::<Gym>()
             .all(conn)
             .await?;
-        Ok(utils::normalize::fort(items, "g"))
+        Ok(utils::normalize::gym(items))
     }
 
     pub async fn bound(
@@ -82,6 +83,7 @@ impl Query {
             .select_only()
             .column(Column::Lat)
             .column(Column::Lon)
+            .column(Column::ExRaidEligible)
             .filter(Column::Lat.between(payload.min_lat, payload.max_lat))
             .filter(Column::Lon.between(payload.min_lon, payload.max_lon))
             .filter(
@@ -94,10 +96,10 @@ impl Query {
             .filter(Column::Deleted.eq(false))
             .filter(Column::Enabled.eq(true))
             .limit(2_000_000)
-            .into_model::<api::point_struct::PointStruct>()
+            .into_model::<Gym>()
             .all(conn)
             .await?;
-        Ok(utils::normalize::fort(items, "g"))
+        Ok(utils::normalize::gym(items))
     }
 
     pub async fn area(
@@ -108,13 +110,13 @@ impl Query {
         let items = Entity::find()
             .from_raw_sql(Statement::from_sql_and_values(
                 DbBackend::MySql,
-                format!("SELECT lat, lon FROM gym WHERE enabled = 1 AND deleted = 0 AND updated > {} AND ({})", last_seen, sql_raw_bbox(area)).as_str(),
+                format!("SELECT lat, lon, ex_raid_eligible FROM gym WHERE enabled = 1 AND deleted = 0 AND updated > {} AND ({})", last_seen, sql_raw_bbox(area)).as_str(),
                 vec![],
             ))
-            .into_model::<api::point_struct::PointStruct>()
+            .into_model::<Gym>()
             .all(conn)
             .await?;
-        Ok(utils::normalize::fort_filtered(items, area, "g"))
+        Ok(utils::normalize::gym_filtered(items, area))
     }
 
     pub async fn stats(
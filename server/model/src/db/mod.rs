@@ -1,5 +1,7 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.10.1
 
+use std::collections::HashMap;
+
 use crate::error::ModelError;
 
 use super::*;
@@ -90,6 +92,13 @@ pub struct Spawnpoint {
     pub despawn_sec: Option<u16>,
 }
 
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct Gym {
+    pub lat: f64,
+    pub lon: f64,
+    pub ex_raid_eligible: Option<u8>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum RdmInstanceArea {
@@ -108,11 +117,56 @@ pub struct RdmInstance {
 pub struct GenericData {
     pub i: String,
     pub p: [f64; 2],
+    /// Relative value of the point when clustering, `1.0` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub w: Option<f64>,
 }
 
 impl GenericData {
     pub fn new(i: String, lat: f64, lon: f64) -> Self {
-        GenericData { i, p: [lat, lon] }
+        GenericData {
+            i,
+            p: [lat, lon],
+            w: None,
+        }
+    }
+
+    pub fn with_weight(mut self, w: Option<f64>) -> Self {
+        self.w = w;
+        self
+    }
+
+    /// Category the point was queried from, based on the id prefixes set in [utils::normalize]
+    pub fn category(&self) -> &str {
+        match self.i.chars().next() {
+            Some('g') => "gym",
+            Some('p') => "pokestop",
+            Some('s') => "station",
+            Some('v') | Some('u') => "spawnpoint",
+            _ => "",
+        }
+    }
+}
+
+pub trait GetWeights {
+    fn get_weights(&self) -> Vec<f64>;
+}
+
+impl GetWeights for Vec<GenericData> {
+    fn get_weights(&self) -> Vec<f64> {
+        self.iter().map(|p| p.w.unwrap_or(1.)).collect()
+    }
+}
+
+pub trait GetRadii {
+    fn get_radii(&self, radii: &HashMap<String, f64>, default: f64) -> Vec<f64>;
+}
+
+impl GetRadii for Vec<GenericData> {
+    fn get_radii(&self, radii: &HashMap<String, f64>, default: f64) -> Vec<f64> {
+        self.iter()
+            .map(|p| radii.get(p.category()).copied().unwrap_or(default))
+            .collect()
     }
 }
 
@@ -29,6 +29,8 @@ pub struct Model {
     pub geometry: Json,
     #[sea_orm(column_type = "custom(\"MEDIUMINT UNSIGNED\")", nullable)]
     pub points: u32,
+    #[serde(default)]
+    pub open_route: bool,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
 }
@@ -61,6 +63,7 @@ pub struct RouteNoGeometry {
     pub description: Option<String>,
     pub mode: Type,
     pub points: u32,
+    pub open_route: bool,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
 }
@@ -78,6 +81,7 @@ impl ToFeatureFromModel for Model {
             geofence_id,
             id,
             mode,
+            open_route,
             ..
         } = self;
 
@@ -102,6 +106,14 @@ impl ToFeatureFromModel for Model {
             },
             geofence_id.clone(),
         );
+        feature.set_property(
+            if internal {
+                "__open_route"
+            } else {
+                "open_route"
+            },
+            open_route,
+        );
         Ok(feature)
     }
 }
@@ -120,6 +132,7 @@ impl Query {
             .column(Column::Name)
             .column(Column::Mode)
             .column(Column::Points)
+            .column(Column::OpenRoute)
             .column(Column::GeofenceId)
             .column(Column::Description)
             .column(Column::CreatedAt)
@@ -160,6 +173,7 @@ impl Query {
                     "description": model.description,
                     "points": model.points,
                     "mode": model.mode,
+                    "open_route": model.open_route,
                 })
             })
             .collect();
@@ -200,6 +214,7 @@ impl Query {
             .column(Column::Description)
             .column(Column::Mode)
             .column(Column::Points)
+            .column(Column::OpenRoute)
             .column(Column::CreatedAt)
             .column(Column::UpdatedAt)
             .order_by(Column::Name, Order::Asc)
@@ -221,6 +236,7 @@ impl Query {
                     geometry: Set(value),
                     mode: Set(incoming.mode),
                     description: Set(incoming.description),
+                    open_route: Set(incoming.open_route),
                     created_at: Set(Utc::now()),
                     updated_at: Set(Utc::now()),
                     ..Default::default()
@@ -281,6 +297,7 @@ impl Query {
             old_model.geofence_id = Set(new_model.geofence_id);
             old_model.geometry = Set(value);
             old_model.mode = Set(new_model.mode);
+            old_model.open_route = Set(new_model.open_route);
             old_model.updated_at = Set(Utc::now());
             old_model.update(db).await
         } else {
@@ -417,6 +434,10 @@ impl Query {
                                 active_model.geofence_id = Set(fence_id as u32);
                                 active_model.geometry = Set(geometry);
                                 active_model.mode = Set(mode);
+                                active_model.open_route = Set(feat
+                                    .property("__open_route")
+                                    .and_then(|open_route| open_route.as_bool())
+                                    .unwrap_or_default());
                                 active_model.updated_at = Set(Utc::now());
                                 if update_bool {
                                     active_model.update(conn).await?;
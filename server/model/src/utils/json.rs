@@ -344,6 +344,10 @@ impl JsonToModel for Value {
                                     mode: Set(mode),
                                     geofence_id: Set(geofence_id as u32),
                                     description: Set(description),
+                                    open_route: Set(incoming
+                                        .get("open_route")
+                                        .and_then(|open_route| open_route.as_bool())
+                                        .unwrap_or_default()),
                                     ..Default::default()
                                 })
                             }
@@ -6,9 +6,14 @@ use super::*;
 
 use crate::{
     api::text::TextHelpers,
-    db::{AreaRef, Spawnpoint, sea_orm_active_enums::Type},
+    db::{AreaRef, Gym, Spawnpoint, sea_orm_active_enums::Type},
 };
 
+/// Spawnpoints without a confirmed despawn timer are assumed to be active for half as long
+const UNCONFIRMED_SPAWNPOINT_WEIGHT: f64 = 0.5;
+/// Gyms that can host EX raids are worth twice as much as regular ones
+const EX_RAID_GYM_WEIGHT: f64 = 2.;
+
 pub trait HasLatLon {
     fn lat(&self) -> f64;
     fn lon(&self) -> f64;
@@ -23,6 +28,15 @@ impl HasLatLon for Spawnpoint {
     }
 }
 
+impl HasLatLon for Gym {
+    fn lat(&self) -> f64 {
+        self.lat
+    }
+    fn lon(&self) -> f64 {
+        self.lon
+    }
+}
+
 impl HasLatLon for api::point_struct::PointStruct {
     fn lat(&self) -> f64 {
         self.lat
@@ -90,6 +104,38 @@ pub fn fort_filtered(
         .collect()
 }
 
+fn gym_weight(item: &Gym) -> Option<f64> {
+    if item.ex_raid_eligible.unwrap_or(0) > 0 {
+        Some(EX_RAID_GYM_WEIGHT)
+    } else {
+        None
+    }
+}
+
+pub fn gym(items: Vec<Gym>) -> Vec<db::GenericData> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            db::GenericData::new(format!("g{}", i), item.lat, item.lon)
+                .with_weight(gym_weight(&item))
+        })
+        .collect()
+}
+
+pub fn gym_filtered(items: Vec<Gym>, area: &FeatureCollection) -> Vec<db::GenericData> {
+    let polygons = AreaPolygons::from_collection(area);
+    items
+        .into_iter()
+        .filter(|item| polygons.contains(item.lat(), item.lon()))
+        .enumerate()
+        .map(|(i, item)| {
+            db::GenericData::new(format!("g{}", i), item.lat, item.lon)
+                .with_weight(gym_weight(&item))
+        })
+        .collect()
+}
+
 pub fn count_in_area<T: HasLatLon>(items: &[T], area: &FeatureCollection) -> i32 {
     let polygons = AreaPolygons::from_collection(area);
     items
@@ -112,6 +158,11 @@ pub fn spawnpoint(items: Vec<db::Spawnpoint>) -> Vec<db::GenericData> {
                 item.lat,
                 item.lon,
             )
+            .with_weight(if item.despawn_sec.is_some() {
+                None
+            } else {
+                Some(UNCONFIRMED_SPAWNPOINT_WEIGHT)
+            })
         })
         .collect()
 }
@@ -135,6 +186,11 @@ pub fn spawnpoint_filtered(
                 item.lat,
                 item.lon,
             )
+            .with_weight(if item.despawn_sec.is_some() {
+                None
+            } else {
+                Some(UNCONFIRMED_SPAWNPOINT_WEIGHT)
+            })
         })
         .collect()
 }